@@ -3,7 +3,9 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use richie_rich_vuejs_box::msg::{QueryAnswer, ExecuteMsg, InstantiateMsg, QueryMsg};
+use richie_rich_vuejs_box::msg::{
+    QueryAnswer, ExecuteMsg, HandleAnswer, InstantiateMsg, MigrateMsg, QueryMsg, ReceiveMsg,
+};
 // use richie_rich_vuejs_box::state::Outcome;
 
 fn main() {
@@ -15,6 +17,9 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     // export_schema(&schema_for!(Outcome), &out_dir);
     export_schema(&schema_for!(QueryAnswer), &out_dir);
+    export_schema(&schema_for!(HandleAnswer), &out_dir);
+    export_schema(&schema_for!(ReceiveMsg), &out_dir);
 }
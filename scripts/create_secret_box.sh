@@ -232,7 +232,7 @@ function main() {
     log -e "LocalSecret LCD: $localsecret_lcd\n"
 
     local init_msg
-    init_msg='{}'
+    init_msg="{\"prng_seed\":\"$(head -c 32 /dev/urandom | base64)\"}"
     code_id="$(upload_contract '.')"
     contract_hash="$(query_contract_hash "$code_id")"
     contract_addr="$(create_contract "$init_msg" "$code_id")"
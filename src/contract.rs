@@ -1,271 +1,1423 @@
 use cosmwasm_std::{
     entry_point, to_binary, Deps, DepsMut, Env,
-    MessageInfo, Response, StdResult, Addr, Binary, StdError, Uint128, 
+    MessageInfo, Response, StdResult, Addr, Api, BankMsg, Binary, Coin, CosmosMsg, StdError, Uint128, Storage,
+    from_binary,
 };
+use cosmwasm_storage::PrefixedStorage;
+use sha2::{Digest, Sha256};
 use secret_toolkit::{
     viewing_key::{ViewingKey, ViewingKeyStore}, 
-    permit::Permit
+    permit::{Permit, RevokedPermits},
+    utils::{pad_handle_result, pad_query_result},
+    snip20,
 };
 
 use crate::{
     error::{ContractError}, 
     msg::{QueryWithPermit, RichieRichPermissions}
 };
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, QueryAnswer};
-use crate::state::{state, state_read, Outcome, NetWorthStore};
+use crate::msg::{
+    ExecuteMsg, HandleAnswer, InstantiateMsg, MigrateMsg, QueryMsg, QueryAnswer, ReceiveMsg, ResponseStatus,
+};
+use crate::state::{
+    legacy_state, percentile_bucket, Config, ContractStatusLevel,
+    CommitmentStore, ContractVersion, DepositStore, EscrowTokenStore, IncludeDelegationsStore, Millionaire, NetWorthStore, Outcome, OutcomeStore, ParticipantCountStore,
+    PrizePoolStore, RankingStore, RevealDeadlineStore, Round, SnapshotHeightStore, Snip20Token, VerifiedDenomsStore, RoundStatus, RoundStore, WinnerRevealStore, CONFIG, CONTRACT_STATUS, CONTRACT_VERSION,
+    DEFAULT_PERCENTILE_BUCKETS,
+};
 
 pub const PREFIX_REVOKED_PERMITS: &str = "revoked_permits";
+/// Appended to `ViewingKey::STORAGE_KEY` by secret-toolkit to store the PRNG seed
+/// Stored as the hashed viewing key of decoys that have none. No key hashes to it,
+/// so they still have no usable key
+const NO_VIEWING_KEY: [u8; 32] = [0; 32];
+/// Responses are padded to a multiple of this size, so their length reveals less about their content
+pub const RESPONSE_BLOCK_SIZE: usize = 256;
+pub const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
+pub const CONTRACT_VERSION_STR: &str = env!("CARGO_PKG_VERSION");
 
 #[entry_point]
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
-    _info: MessageInfo,
-    _msg: InstantiateMsg,
+    env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+
+    let admin = match msg.admin {
+        Some(admin) => deps.api.addr_validate(&admin)?,
+        None => info.sender.clone(),
+    };
+
+    let buckets = msg.percentile_buckets.unwrap_or_else(|| DEFAULT_PERCENTILE_BUCKETS.to_vec());
+    let ascending = buckets.windows(2).all(|w| w[0] < w[1]);
+    if buckets.is_empty() || !ascending || buckets.iter().any(|b| *b == 0 || *b > 100) {
+        return Err(StdError::generic_err(
+            "Percentile buckets must be ascending percentages between 1 and 100",
+        ));
+    }
+
+    let config = Config {
+        admin,
+        pending_admin: None,
+        percentile_buckets: buckets,
+    };
+    CONFIG.save(deps.storage, &config)?;
+    CONTRACT_STATUS.save(deps.storage, &ContractStatusLevel::NormalRun)?;
+    save_contract_version(deps.storage)?;
+    ViewingKey::set_seed(deps.storage, msg.prng_seed.as_slice());
+
+    // the first round (id 0) is opened on instantiation, so the contract is ready to use
+    RoundStore::create(deps.storage, &Round::new(info.sender, None, None, None), env.block.height)?;
+
+    Ok(Response::default())
+}
+
+#[entry_point]
+pub fn migrate(
+    deps: DepsMut,
+    env: Env,
+    msg: MigrateMsg,
 ) -> StdResult<Response> {
+    // contracts deployed before viewing keys could be created have no seed for them. Existing
+    // keys are stored hashed, so they stay valid when the seed changes
+    ViewingKey::set_seed(deps.storage, msg.prng_seed.as_slice());
+
+    match CONTRACT_VERSION.may_load(deps.storage)? {
+        Some(stored) => {
+            if stored.contract != CONTRACT_NAME {
+                return Err(StdError::generic_err(format!(
+                    "Cannot migrate from a different contract: {}", stored.contract
+                )));
+            }
+            let stored_version = parse_version(&stored.version)?;
+            if stored_version > parse_version(CONTRACT_VERSION_STR)? {
+                return Err(StdError::generic_err(format!(
+                    "Cannot migrate from newer version {stored_version}"
+                )));
+            }
+        },
+        // contracts deployed before rounds were introduced have no version record
+        None => migrate_legacy(deps.storage, deps.api, env.block.height, msg)?,
+    }
 
-    let init_state = Outcome::init();
-    // demonstates how to use Singleton
-    state(deps.storage).save(&init_state)?;
+    save_contract_version(deps.storage)?;
 
     Ok(Response::default())
 }
 
+/// Moves the single `Outcome` and the address-keyed networths of the original contract into round 0,
+/// and sets up the config and status that the original contract did not have.
+/// The original contract kept no list of participants, so only the richest and `legacy_participants`
+/// are migrated. The networths of anyone else are lost, as they are never read again
+fn migrate_legacy(storage: &mut dyn Storage, api: &dyn Api, height: u64, msg: MigrateMsg) -> StdResult<()> {
+    let legacy_outcome = match legacy_state(storage).may_load()? {
+        Some(outcome) => outcome,
+        None => return Err(StdError::generic_err("No contract state found to migrate")),
+    };
+    let admin = match msg.admin {
+        Some(admin) => api.addr_validate(&admin)?,
+        None => return Err(StdError::generic_err(
+            "An admin must be provided when migrating from a contract without one",
+        )),
+    };
+
+    let config = Config {
+        admin: admin.clone(),
+        pending_admin: None,
+        percentile_buckets: DEFAULT_PERCENTILE_BUCKETS.to_vec(),
+    };
+    CONFIG.save(storage, &config)?;
+    CONTRACT_STATUS.save(storage, &ContractStatusLevel::NormalRun)?;
+    let round_id = RoundStore::create(storage, &Round::new(admin, None, None, None), height)?;
+
+    // The legacy richest goes into the ranking first, so it stays ahead of anyone with an equal networth
+    let mut participants = vec![legacy_outcome.richest.addr.clone()];
+    for participant in msg.legacy_participants.unwrap_or_default() {
+        let addr = api.addr_validate(&participant)?;
+        if !participants.contains(&addr) {
+            participants.push(addr);
+        }
+    }
+    for addr in participants {
+        if let Some(networth) = NetWorthStore::take_legacy(storage, &addr)? {
+            let ranking_id = RankingStore::insert(storage, round_id, Millionaire { addr: addr.clone(), networth })?;
+            NetWorthStore::save(storage, round_id, &addr, networth, ranking_id)?;
+        }
+    }
+    // a richest without a networth would leave the outcome pointing at someone outside the ranking
+    let richest = &legacy_outcome.richest;
+    let migrated = NetWorthStore::may_load(storage, round_id, &richest.addr)?;
+    if richest.addr.as_str() != "" && migrated != Some(richest.networth) {
+        return Err(StdError::generic_err(format!(
+            "No networth found to migrate for the richest, {}", richest.addr
+        )));
+    }
+    let participants = RankingStore::len(storage, round_id)?;
+    ParticipantCountStore::save(storage, round_id, participants)?;
+
+    OutcomeStore::save(storage, round_id, &legacy_outcome)?;
+    legacy_state(storage).remove();
+
+    Ok(())
+}
+
+fn save_contract_version(storage: &mut dyn Storage) -> StdResult<()> {
+    let version = ContractVersion {
+        contract: CONTRACT_NAME.to_string(),
+        version: CONTRACT_VERSION_STR.to_string(),
+    };
+    CONTRACT_VERSION.save(storage, &version)
+}
+
+fn parse_version(version: &str) -> StdResult<semver::Version> {
+    semver::Version::parse(version).map_err(|err| StdError::generic_err(err.to_string()))
+}
+
 #[entry_point]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
-    match msg {
-        ExecuteMsg::SubmitNetWorth { networth } => try_submit_net_worth(deps, info, networth),
-        ExecuteMsg::SetViewingKey { key } => try_set_key(deps, info, key),
+    let contract_status = CONTRACT_STATUS.load(deps.storage)?;
+    let allowed = match contract_status {
+        ContractStatusLevel::NormalRun => true,
+        ContractStatusLevel::StopSubmissions => !matches!(
+            msg,
+            ExecuteMsg::SubmitNetWorth { .. }
+                | ExecuteMsg::SubmitVerifiedNetWorth { .. }
+                | ExecuteMsg::Receive { .. }
+                | ExecuteMsg::CommitNetWorth { .. }
+                | ExecuteMsg::RevealNetWorth { .. }
+                | ExecuteMsg::UpdateNetWorth { .. }
+        ),
+        ContractStatusLevel::StopAll => matches!(
+            msg,
+            ExecuteMsg::SetViewingKey { .. }
+                | ExecuteMsg::CreateViewingKey { .. }
+                | ExecuteMsg::RevokePermit { .. }
+                | ExecuteMsg::SetContractStatus { .. }
+        ),
+    };
+    if !allowed {
+        return Err(ContractError::ContractStopped {});
     }
+    // only submissions accept funds, for their entry fee
+    let is_submission = matches!(msg, ExecuteMsg::SubmitNetWorth { .. } | ExecuteMsg::SubmitVerifiedNetWorth { .. });
+    if !info.funds.is_empty() && !is_submission {
+        return Err(ContractError::UnexpectedFunds {});
+    }
+
+    let response = match msg {
+        ExecuteMsg::CreateRound {
+            end_height, end_time, entry_fee, verified_denoms, include_delegations, escrow_token, reveal_deadline, ..
+        } => {
+            let include_delegations = include_delegations.unwrap_or_default();
+            try_create_round(
+                deps, env, info, end_height, end_time, entry_fee, verified_denoms, include_delegations, escrow_token,
+                reveal_deadline,
+            )
+        },
+        ExecuteMsg::CloseRound { round_id, .. } => try_close_round(deps, env, info, round_id),
+        ExecuteMsg::CancelRound { round_id, .. } => try_cancel_round(deps, info, round_id),
+        ExecuteMsg::SubmitNetWorth { round_id, networth, decoys, .. } => try_submit_net_worth(deps, env, info, round_id, networth, decoys),
+        ExecuteMsg::SubmitVerifiedNetWorth { round_id, decoys, .. } => {
+            try_submit_verified_net_worth(deps, env, info, round_id, decoys)
+        },
+        ExecuteMsg::Receive { from, amount, msg, .. } => try_receive(deps, env, info, from, amount, msg),
+        ExecuteMsg::Withdraw { round_id, .. } => try_withdraw(deps, env, info, round_id),
+        ExecuteMsg::CommitNetWorth { round_id, commitment, .. } => try_commit_net_worth(deps, env, info, round_id, commitment),
+        ExecuteMsg::RevealNetWorth { round_id, networth, salt, .. } => {
+            try_reveal_net_worth(deps, env, info, round_id, networth, salt)
+        },
+        ExecuteMsg::UpdateNetWorth { round_id, networth, .. } => try_update_net_worth(deps, env, info, round_id, networth),
+        ExecuteMsg::WithdrawSubmission { round_id, .. } => try_withdraw_submission(deps, env, info, round_id),
+        ExecuteMsg::SetWinnerReveal { round_id, reveal_networth, .. } => try_set_winner_reveal(deps, info, round_id, reveal_networth),
+        ExecuteMsg::FinalizeRound { round_id, .. } => try_finalize_round(deps, env, round_id),
+        ExecuteMsg::ClaimPrize { round_id, .. } => try_claim_prize(deps, round_id),
+        ExecuteMsg::SweepPrizePool { round_id, .. } => try_sweep_prize_pool(deps, info, round_id),
+        ExecuteMsg::SetViewingKey { key, decoys, .. } => try_set_key(deps, info, key, decoys),
+        ExecuteMsg::CreateViewingKey { entropy, .. } => try_create_key(deps, env, info, entropy),
+        ExecuteMsg::RevokePermit { permit_name, .. } => try_revoke_permit(deps, info, permit_name),
+        ExecuteMsg::ChangeAdmin { address, .. } => try_change_admin(deps, info, address),
+        ExecuteMsg::AcceptAdmin { .. } => try_accept_admin(deps, info),
+        ExecuteMsg::SetContractStatus { level, .. } => try_set_contract_status(deps, info, level),
+    };
+
+    pad_handle_result(response, RESPONSE_BLOCK_SIZE)
 }
 
 #[entry_point]
-pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
     let q_response = match msg {
         // There's some repeated code which can be moved into a separate function.
         // We've laid it our this way for clarity
-        QueryMsg::AllInfo { .. } => {
-            //
-            // complete code here
-            // 
-            ()
+        QueryMsg::AllInfo { round_id, .. } => {
+            let (address, validated_key) = msg.get_validation_params(deps.api)?;
+            let result = ViewingKey::check(deps.storage, address.as_str(), validated_key.as_str());
+            match result.is_ok() {
+                true => query_all_info(deps, round_id, address),
+                false => Err(ContractError::Unauthorized {}),
+            }
+        },
+        QueryMsg::AmIRichest { round_id, .. } => {
+            let (address, validated_key) = msg.get_validation_params(deps.api)?;
+            let result = ViewingKey::check(deps.storage, address.as_str(), validated_key.as_str());
+            match result.is_ok() {
+                true => query_richest(deps, round_id, address),
+                false => Err(ContractError::Unauthorized {}),
+            }
         },
-        QueryMsg::AmIRichest { .. } => {
-            //
-            // complete code here
-            // 
-            ()
+        QueryMsg::MyRank { round_id, .. } => {
+            let (address, validated_key) = msg.get_validation_params(deps.api)?;
+            let result = ViewingKey::check(deps.storage, address.as_str(), validated_key.as_str());
+            match result.is_ok() {
+                true => query_my_rank(deps, round_id, address),
+                false => Err(ContractError::Unauthorized {}),
+            }
         },
-        //
-        // complete code here
-        // 
+        QueryMsg::MyPercentile { round_id, .. } => {
+            let (address, validated_key) = msg.get_validation_params(deps.api)?;
+            let result = ViewingKey::check(deps.storage, address.as_str(), validated_key.as_str());
+            match result.is_ok() {
+                true => query_my_percentile(deps, round_id, address),
+                false => Err(ContractError::Unauthorized {}),
+            }
+        },
+        QueryMsg::Batch { ref queries, .. } => {
+            let (address, validated_key) = msg.get_validation_params(deps.api)?;
+            let result = ViewingKey::check(deps.storage, address.as_str(), validated_key.as_str());
+            match result.is_ok() {
+                true => authenticated_query(deps, address, QueryWithPermit::Batch { queries: queries.clone() }),
+                false => Err(ContractError::Unauthorized {}),
+            }
+        },
+        QueryMsg::WithPermit { permit, query, .. } => permit_queries(deps, env, permit, query),
+        QueryMsg::RoundStatus { round_id, .. } => query_round_status(deps, env, round_id),
+        QueryMsg::ContestInfo { round_id, .. } => query_contest_info(deps, env, round_id),
+        QueryMsg::Winner { round_id, .. } => query_winner(deps, round_id),
+        QueryMsg::Config { .. } => query_config(deps),
+        QueryMsg::ContractStatus { .. } => query_contract_status(deps),
     };
 
-    to_binary( /* complete code here */ "placeholder")
+    let result = q_response.and_then(|answer| Ok(to_binary(&answer)?));
+    pad_query_result(result, RESPONSE_BLOCK_SIZE)
 }
 
-fn permit_queries(deps: Deps, env: Env, permit: Permit /* add generic */, query: QueryWithPermit) -> (/* complete code here */) {
+fn permit_queries(
+    deps: Deps,
+    env: Env,
+    permit: Permit<RichieRichPermissions>,
+    query: QueryWithPermit,
+) -> Result<QueryAnswer, ContractError> {
     // Validate permit content
     let contract_address = env.contract.address;
-        //
-        // complete code here
-        // 
+
+    // a revoked permit, a bad signature or another contract's permit are all rejected the same way
+    let account = secret_toolkit::permit::validate(
+        deps,
+        PREFIX_REVOKED_PERMITS,
+        &permit,
+        contract_address.into_string(),
+        None,
+    ).map_err(|_| ContractError::Unauthorized {})?;
 
     // Permit validated! We can now execute the query.
-        //
-        // complete code here
-        // 
+    let required = query.required_permissions();
+    if !required.iter().all(|permission| permit.check_permission(permission)) {
+        return Err(ContractError::NoPermission {
+            required,
+            granted: permit.params.permissions,
+        });
+    }
+
+    authenticated_query(deps, deps.api.addr_validate(&account)?, query)
+}
+
+/// Runs a query on behalf of `account`, which must already be authenticated
+fn authenticated_query(deps: Deps, account: Addr, query: QueryWithPermit) -> Result<QueryAnswer, ContractError> {
+    match query {
+        QueryWithPermit::AllInfo { round_id } => query_all_info(deps, round_id, account),
+        QueryWithPermit::AmIRichest { round_id } => query_richest(deps, round_id, account),
+        QueryWithPermit::MyRank { round_id } => query_my_rank(deps, round_id, account),
+        QueryWithPermit::MyPercentile { round_id } => query_my_percentile(deps, round_id, account),
+        QueryWithPermit::Batch { queries } => {
+            let answers = queries
+                .into_iter()
+                .map(|query| match query {
+                    QueryWithPermit::Batch { .. } => Err(StdError::generic_err("Batch queries cannot be nested").into()),
+                    query => authenticated_query(deps, account.clone(), query),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
 
+            Ok(QueryAnswer::Batch { answers })
+        }
+    }
 }
 
 
+#[allow(clippy::too_many_arguments)]
+pub fn try_create_round(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    end_height: Option<u64>,
+    end_time: Option<u64>,
+    entry_fee: Option<Coin>,
+    verified_denoms: Option<Vec<String>>,
+    include_delegations: bool,
+    escrow_token: Option<Snip20Token>,
+    reveal_deadline: Option<u64>,
+) -> Result<Response, ContractError> {
+    if matches!(&entry_fee, Some(fee) if fee.amount.is_zero()) {
+        return Err(StdError::generic_err("The entry fee cannot be zero").into());
+    }
+    if matches!(&verified_denoms, Some(denoms) if denoms.is_empty()) {
+        return Err(StdError::generic_err("At least one denom must be verified").into());
+    }
+    if include_delegations && verified_denoms.is_none() {
+        return Err(StdError::generic_err("Delegations can only be included with verified denoms").into());
+    }
+    if escrow_token.is_some() && (entry_fee.is_some() || verified_denoms.is_some()) {
+        return Err(StdError::generic_err("Escrowed rounds cannot have an entry fee or verified denoms").into());
+    }
+    if let Some(deadline) = reveal_deadline {
+        if entry_fee.is_some() || verified_denoms.is_some() || escrow_token.is_some() {
+            return Err(StdError::generic_err(
+                "Commit-reveal rounds cannot have an entry fee, verified denoms or an escrow token"
+            ).into());
+        }
+        // the reveal deadline is a block height, so the round must end at a height before it
+        match end_height {
+            None => return Err(StdError::generic_err("Commit-reveal rounds need an end height").into()),
+            Some(end) if deadline <= end => {
+                return Err(StdError::generic_err("The reveal deadline must be after the end of the round").into());
+            },
+            Some(_) => {},
+        }
+    }
+    let round = Round::new(info.sender, end_height, end_time, entry_fee);
+    let round_id = RoundStore::create(deps.storage, &round, env.block.height)?;
+    if let Some(deadline) = reveal_deadline {
+        RevealDeadlineStore::save(deps.storage, round_id, deadline)?;
+    }
+    if let Some(denoms) = verified_denoms {
+        VerifiedDenomsStore::save(deps.storage, round_id, denoms)?;
+        IncludeDelegationsStore::save(deps.storage, round_id, include_delegations)?;
+    }
+    let mut response = Response::new().add_attribute("round_id", round_id.to_string());
+    if let Some(token) = escrow_token {
+        let token = Snip20Token { address: deps.api.addr_validate(token.address.as_str())?, ..token };
+        EscrowTokenStore::save(deps.storage, round_id, &token)?;
+        // the token notifies the contract of deposits through `Receive`
+        let register = snip20::register_receive_msg(
+            env.contract.code_hash,
+            None,
+            RESPONSE_BLOCK_SIZE,
+            token.code_hash,
+            token.address.into_string(),
+        )?;
+        response = response.add_message(register);
+    }
+
+    Ok(response)
+}
+
 pub fn try_submit_net_worth(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
+    round_id: u64,
     networth: Uint128,
+    decoys: Option<Vec<Addr>>,
 ) -> Result<Response, ContractError> {
-    // checks that account has not already submitted -- can only submit once
-    match NetWorthStore::may_load(deps.storage, &info.sender) {
-        Some(networth) => return Err(ContractError::AlreadySubmittedNetworth { networth }),
-        None => {
-            // saves submission for each address can view their submission
-            NetWorthStore::save(deps.storage, &info.sender, networth)?;
-        },
+    let round = load_open_round(deps.as_ref(), &env, round_id)?;
+    if VerifiedDenomsStore::may_load(deps.storage, round_id)?.is_some() {
+        return Err(ContractError::VerificationRequired { round_id });
+    }
+    if EscrowTokenStore::may_load(deps.storage, round_id)?.is_some() {
+        return Err(ContractError::EscrowRequired { round_id });
     }
+    if RevealDeadlineStore::may_load(deps.storage, round_id)?.is_some() {
+        return Err(ContractError::CommitRequired { round_id });
+    }
+    check_entry_fee(&round, &info.funds)?;
 
-    // Compares networth with current highest, and update state if necessary
-    // For simplicity, if networth is equal, the first Millionaire remains the richest
-    let mut outcome = state(deps.storage).load()?;
+    let became_richest = submit(deps, info.sender, round_id, &round, networth, None, decoys)?;
 
-    match networth > outcome.richest.networth {
-        true => outcome.update_richest(info.sender, networth),
-        false => (),
+    let answer = HandleAnswer::SubmitNetWorth { status: ResponseStatus::Success, became_richest };
+    Ok(Response::new().set_data(to_binary(&answer)?))
+}
+
+/// Submits the sender's balances in the round's verified denoms, so the networth cannot be made up
+pub fn try_submit_verified_net_worth(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    round_id: u64,
+    decoys: Option<Vec<Addr>>,
+) -> Result<Response, ContractError> {
+    let round = load_open_round(deps.as_ref(), &env, round_id)?;
+    let denoms = VerifiedDenomsStore::may_load(deps.storage, round_id)?
+        .ok_or(ContractError::NotVerifiedRound { round_id })?;
+    check_entry_fee(&round, &info.funds)?;
+
+    let mut networth = Uint128::zero();
+    for denom in &denoms {
+        let balance = deps.querier.query_balance(&info.sender, denom)?;
+        networth = networth.checked_add(balance.amount).map_err(StdError::from)?;
+    }
+    if IncludeDelegationsStore::load(deps.storage, round_id)? {
+        for delegation in deps.querier.query_all_delegations(&info.sender)? {
+            if denoms.contains(&delegation.amount.denom) {
+                networth = networth.checked_add(delegation.amount.amount).map_err(StdError::from)?;
+            }
+        }
     }
+    let snapshot_height = Some(env.block.height);
+    let became_richest = submit(deps, info.sender, round_id, &round, networth, snapshot_height, decoys)?;
 
-    // save updated outcome on who's richest
-    state(deps.storage).save(&outcome)?;
+    let answer = HandleAnswer::SubmitVerifiedNetWorth { status: ResponseStatus::Success, networth, became_richest };
+    Ok(Response::new().set_data(to_binary(&answer)?))
+}
+
+/// Handles tokens sent by a SNIP-20 contract, which must be the escrow token of the round
+/// they are deposited in. The deposit is submitted as the networth of `from`
+pub fn try_receive(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    from: Addr,
+    amount: Uint128,
+    msg: Option<Binary>,
+) -> Result<Response, ContractError> {
+    let msg = msg.ok_or_else(|| StdError::generic_err("Tokens must be sent with a message"))?;
+    match from_binary(&msg)? {
+        ReceiveMsg::Deposit { round_id, decoys } => {
+            let round = load_open_round(deps.as_ref(), &env, round_id)?;
+            match EscrowTokenStore::may_load(deps.storage, round_id)? {
+                Some(token) if token.address == info.sender => (),
+                _ => return Err(ContractError::InvalidToken { round_id }),
+            }
+            submit(deps.branch(), from.clone(), round_id, &round, amount, None, decoys.clone())?;
+            for account in with_decoys(&from, decoys) {
+                let deposit = if account == from { amount } else { Uint128::zero() };
+                DepositStore::add(deps.storage, round_id, &account, deposit)?;
+            }
+        },
+    }
 
     Ok(Response::new())
 }
 
-pub fn try_set_key(deps: DepsMut, info: MessageInfo, key: String) -> Result<Response, ContractError> {
-    ViewingKey::set(deps.storage, info.sender.as_str(), key.as_str());
-    Ok(Response::new())
+/// Returns a deposit of the escrow token, once the round no longer accepts submissions
+pub fn try_withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    round_id: u64,
+) -> Result<Response, ContractError> {
+    let round = load_round(deps.as_ref(), round_id)?;
+    if round.status(&env.block) == RoundStatus::Open {
+        return Err(ContractError::EscrowLocked { round_id });
+    }
+    let amount = DepositStore::take(deps.storage, round_id, &info.sender)?
+        .ok_or(ContractError::NothingToWithdraw { round_id })?;
+    let transfer = escrow_transfer_msg(deps.as_ref(), round_id, info.sender, amount)?;
+
+    let answer = HandleAnswer::Withdraw { status: ResponseStatus::Success, amount };
+    Ok(Response::new().add_message(transfer).set_data(to_binary(&answer)?))
 }
 
-fn query_all_info(
-    deps: Deps,
-    addr: Addr,
-) -> StdResult<QueryAnswer> {
-    let outcome = state_read(deps.storage).load()?;
-    let richest = outcome.richest.addr == addr;
-    let may_networth = NetWorthStore::may_load(deps.storage, &addr);
-    let networth = match may_networth {
-        Some(x) => x,
-        None => return Err(StdError::GenericErr { msg: "You have not submitted your networth".to_string() }),
-    };
+/// Transfers `amount` of the round's escrow token to `recipient`
+fn escrow_transfer_msg(deps: Deps, round_id: u64, recipient: Addr, amount: Uint128) -> Result<CosmosMsg, ContractError> {
+    let token = EscrowTokenStore::may_load(deps.storage, round_id)?
+        .ok_or(ContractError::NothingToWithdraw { round_id })?;
+    let msg = snip20::transfer_msg(
+        recipient.into_string(),
+        amount,
+        None,
+        None,
+        RESPONSE_BLOCK_SIZE,
+        token.code_hash,
+        token.address.into_string(),
+    )?;
+    Ok(msg)
+}
 
-    let resp = QueryAnswer::AllInfo { 
-        richest,
-        networth,
-    };
-        
-    Ok(resp)
+/// Seals the sender's networth, which only takes part in the round once revealed
+pub fn try_commit_net_worth(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    round_id: u64,
+    commitment: Binary,
+) -> Result<Response, ContractError> {
+    load_open_round(deps.as_ref(), &env, round_id)?;
+    if RevealDeadlineStore::may_load(deps.storage, round_id)?.is_none() {
+        return Err(ContractError::NotCommitRevealRound { round_id });
+    }
+    if commitment.len() != 32 {
+        return Err(StdError::generic_err("The commitment must be a sha256 hash").into());
+    }
+    if CommitmentStore::may_load(deps.storage, round_id, &info.sender)?.is_some() {
+        return Err(ContractError::AlreadyCommitted {});
+    }
+    CommitmentStore::save(deps.storage, round_id, &info.sender, &commitment)?;
+
+    let answer = HandleAnswer::CommitNetWorth { status: ResponseStatus::Success };
+    Ok(Response::new().set_data(to_binary(&answer)?))
 }
 
-fn query_richest(
-    deps: Deps,
-    addr: Addr,
-) -> StdResult<QueryAnswer> {
-    let outcome = state_read(deps.storage).load()?;
-    let richest = outcome.richest.addr == addr;
+/// Submits a committed networth, between the end of the round and its reveal deadline
+pub fn try_reveal_net_worth(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    round_id: u64,
+    networth: Uint128,
+    salt: String,
+) -> Result<Response, ContractError> {
+    let round = load_round(deps.as_ref(), round_id)?;
+    let deadline = RevealDeadlineStore::may_load(deps.storage, round_id)?
+        .ok_or(ContractError::NotCommitRevealRound { round_id })?;
+    if round.status(&env.block) != RoundStatus::Closing || env.block.height >= deadline {
+        return Err(ContractError::NotRevealPeriod { round_id });
+    }
+    let commitment = CommitmentStore::may_load(deps.storage, round_id, &info.sender)?
+        .ok_or(ContractError::NotCommitted {})?;
+    if commitment != commitment_of(networth, &salt) {
+        return Err(ContractError::InvalidReveal {});
+    }
 
-    let resp = QueryAnswer::AmIRichest {
-        richest,
-    };
-        
-    Ok(resp)
+    CommitmentStore::remove(deps.storage, round_id, &info.sender);
+    let became_richest = submit(deps, info.sender, round_id, &round, networth, None, None)?;
+
+    let answer = HandleAnswer::RevealNetWorth { status: ResponseStatus::Success, became_richest };
+    Ok(Response::new().set_data(to_binary(&answer)?))
 }
 
+/// sha256 of the networth as 16 big-endian bytes, followed by the salt. The fixed width keeps
+/// the same commitment from opening to different networths with different salts
+fn commitment_of(networth: Uint128, salt: &str) -> Binary {
+    let mut hasher = Sha256::new();
+    hasher.update(networth.u128().to_be_bytes());
+    hasher.update(salt.as_bytes());
+    Binary::from(hasher.finalize().as_slice())
+}
 
-#[cfg(test)]
-mod tests {
-    use std::any::Any;
+/// Records a submission, whether self-reported, verified, escrowed or revealed, and returns whether
+/// the sender became the richest of the round. Callers check the entry fee
+fn submit(
+    deps: DepsMut,
+    sender: Addr,
+    round_id: u64,
+    round: &Round,
+    networth: Uint128,
+    snapshot_height: Option<u64>,
+    decoys: Option<Vec<Addr>>,
+) -> Result<bool, ContractError> {
+    // the ranking is updated first, as the id of the new node is stored with the networth
+    let ranking_id = RankingStore::insert(deps.storage, round_id, Millionaire { addr: sender.clone(), networth })?;
+    for account in with_decoys(&sender, decoys) {
+        if account != sender {
+            NetWorthStore::touch(deps.storage, round_id, &account)?;
+            if snapshot_height.is_some() {
+                SnapshotHeightStore::touch(deps.storage, round_id, &account)?;
+            }
+            continue;
+        }
+        // checks that account has not already submitted -- can only submit once per round
+        match NetWorthStore::may_load(deps.storage, round_id, &sender)? {
+            Some(networth) => return Err(ContractError::AlreadySubmittedNetworth { networth }),
+            // saves submission for each address can view their submission
+            None => NetWorthStore::save(deps.storage, round_id, &sender, networth, ranking_id)?,
+        }
+        if let Some(height) = snapshot_height {
+            // read like the heights of the decoys, which are touched
+            SnapshotHeightStore::may_load(deps.storage, round_id, &sender)?;
+            SnapshotHeightStore::save(deps.storage, round_id, &sender, height)?;
+        }
+    }
+    let participants = ParticipantCountStore::load(deps.storage, round_id)?;
+    ParticipantCountStore::save(deps.storage, round_id, participants + 1)?;
+    if let Some(fee) = &round.entry_fee {
+        let pool = PrizePoolStore::load(deps.storage, round_id)?;
+        PrizePoolStore::save(deps.storage, round_id, pool + fee.amount)?;
+    }
 
-    use crate::state::Millionaire;
+    // Compares networth with current highest, and update state if necessary
+    // For simplicity, if networth is equal, the first Millionaire remains the richest.
+    // To keep gas usage independent of the result, the richest is picked without branching
+    // and the outcome is rewritten even if it did not change
+    let mut outcome = OutcomeStore::load(deps.storage, round_id)?;
 
-    use super::*;
+    let became_richest = networth > outcome.richest.networth;
+    let candidates = [outcome.richest.clone(), Millionaire { addr: sender, networth }];
+    let richest = &candidates[became_richest as usize];
+    outcome.update_richest(richest.addr.clone(), richest.networth);
 
-    use cosmwasm_std::testing::{
-        mock_env, mock_info, mock_dependencies,
-        MockStorage, MockApi, MockQuerier
-    };
-    use cosmwasm_std::{coins, OwnedDeps, from_binary};
+    // save updated outcome on who's richest
+    OutcomeStore::save(deps.storage, round_id, &outcome)?;
 
-    fn init_helper() -> (
-        StdResult<Response>, 
-        OwnedDeps<MockStorage, MockApi, MockQuerier>,
-    ) {
-        let mut deps = mock_dependencies();
-        let msg = InstantiateMsg {};
-        let info = mock_info("creator", &coins(0, "coins"));
-        let res = instantiate(deps.as_mut(), mock_env(), info, msg);
+    Ok(became_richest)
+}
 
-        (res, deps)
+/// Checks that the funds sent with a submission are exactly the round's entry fee
+fn check_entry_fee(round: &Round, funds: &[Coin]) -> Result<(), ContractError> {
+    match &round.entry_fee {
+        Some(fee) if funds != [fee.clone()] => Err(ContractError::InvalidEntryFee { expected: fee.clone() }),
+        None if !funds.is_empty() => Err(ContractError::UnexpectedFunds {}),
+        _ => Ok(()),
     }
+}
 
-    fn submit_networth_helper(
-        deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
-        submissions: Vec<(&str, u128)>
-    ) -> Vec<Response>  {
-        let mut res_vec = vec![];
-        for (sender, networth) in submissions {
-            let msg = ExecuteMsg::SubmitNetWorth {networth: Uint128::from(networth)};
-            let info = mock_info(sender, &[]);
-            let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-            res_vec.push(res);
-        }
-        res_vec
+pub fn try_update_net_worth(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    round_id: u64,
+    networth: Uint128,
+) -> Result<Response, ContractError> {
+    load_open_round(deps.as_ref(), &env, round_id)?;
+    // a verified, escrowed or committed networth cannot be replaced with a self-reported one
+    if VerifiedDenomsStore::may_load(deps.storage, round_id)?.is_some() {
+        return Err(ContractError::VerificationRequired { round_id });
+    }
+    if EscrowTokenStore::may_load(deps.storage, round_id)?.is_some() {
+        return Err(ContractError::EscrowRequired { round_id });
+    }
+    if RevealDeadlineStore::may_load(deps.storage, round_id)?.is_some() {
+        return Err(ContractError::CommitRequired { round_id });
     }
 
-    fn assert_info(deps: Deps, acc: &str, exp_richest: bool, exp_networth: u128) {
-        let res = query_all_info(deps, Addr::unchecked(acc)).unwrap();
+    let ranking_id = match NetWorthStore::may_load_ranking_id(deps.storage, round_id, &info.sender)? {
+        Some(ranking_id) => ranking_id,
+        None => return Err(ContractError::NotSubmitted {}),
+    };
 
-        match res {
-            QueryAnswer::AllInfo { richest, networth } => {
-                assert_eq!(richest, exp_richest); assert_eq!(networth, Uint128::from(exp_networth));       
-            },
-            res => panic!("unexpected QueryAnswer type: {res:?}"),
+    // An update counts as a new submission when breaking ties
+    RankingStore::remove(deps.storage, round_id, ranking_id)?;
+    let ranking_id = RankingStore::insert(deps.storage, round_id, Millionaire { addr: info.sender.clone(), networth })?;
+    NetWorthStore::save(deps.storage, round_id, &info.sender, networth, ranking_id)?;
+
+    // The richest may have changed in either direction, so it's taken from the top of the ranking
+    recompute_richest(deps.storage, round_id)?;
+
+    let richest = OutcomeStore::load(deps.storage, round_id)?.richest.addr == info.sender;
+    let answer = HandleAnswer::UpdateNetWorth { status: ResponseStatus::Success, richest };
+    Ok(Response::new().set_data(to_binary(&answer)?))
+}
+
+pub fn try_withdraw_submission(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    round_id: u64,
+) -> Result<Response, ContractError> {
+    let round = load_round(deps.as_ref(), round_id)?;
+    let ranking_id = NetWorthStore::may_load_ranking_id(deps.storage, round_id, &info.sender)?;
+    // submissions are fixed once the round has ended, while an unrevealed commitment takes no part
+    // in the round, so it can be withdrawn at any time
+    if ranking_id.is_some() {
+        match round.status(&env.block) {
+            RoundStatus::Open | RoundStatus::Cancelled => {},
+            RoundStatus::Closing => return Err(ContractError::RoundClosed { round_id }),
+            RoundStatus::Finalized => return Err(ContractError::RoundAlreadyFinalized { round_id }),
         }
     }
+    let mut vk_store = PrefixedStorage::new(deps.storage, ViewingKey::STORAGE_KEY);
+    let has_key = vk_store.get(info.sender.as_bytes()).is_some();
+    // ViewingKeyStore has no removal, so the hashed key is deleted from its storage directly
+    vk_store.remove(info.sender.as_bytes());
+    let committed = CommitmentStore::may_load(deps.storage, round_id, &info.sender)?.is_some();
+    let reveals = WinnerRevealStore::load(deps.storage, round_id, &info.sender)?;
+    if ranking_id.is_none() && !committed && !reveals && !has_key {
+        return Err(ContractError::NotSubmitted {});
+    }
 
-    fn assert_info_vec(deps: Deps, acc_richest_networth: Vec<(&str, bool, u128)>) {
-        for (acc, exp_richest, exp_networth) in acc_richest_networth {
-            assert_info(deps, acc, exp_richest, exp_networth);
+    // everything stored for the sender is erased, whether or not it has a submission left
+    CommitmentStore::remove(deps.storage, round_id, &info.sender);
+    WinnerRevealStore::remove(deps.storage, round_id, &info.sender);
+    NetWorthStore::remove(deps.storage, round_id, &info.sender);
+    SnapshotHeightStore::remove(deps.storage, round_id, &info.sender);
+
+    let answer = HandleAnswer::WithdrawSubmission { status: ResponseStatus::Success };
+    let mut response = Response::new().set_data(to_binary(&answer)?);
+    if let Some(ranking_id) = ranking_id {
+        RankingStore::remove(deps.storage, round_id, ranking_id)?;
+        let participants = ParticipantCountStore::load(deps.storage, round_id)?;
+        ParticipantCountStore::save(deps.storage, round_id, participants.saturating_sub(1))?;
+        recompute_richest(deps.storage, round_id)?;
+
+        // entry fees are only refunded when the round is cancelled, otherwise they stay in the prize pool
+        if let Some(fee) = round.entry_fee.filter(|_| round.cancelled) {
+            let pool = PrizePoolStore::load(deps.storage, round_id)?;
+            PrizePoolStore::save(deps.storage, round_id, pool.checked_sub(fee.amount).map_err(StdError::from)?)?;
+            response = response.add_message(BankMsg::Send { to_address: info.sender.to_string(), amount: vec![fee] });
         }
     }
+    if let Some(amount) = DepositStore::take(deps.storage, round_id, &info.sender)? {
+        response = response.add_message(escrow_transfer_msg(deps.as_ref(), round_id, info.sender, amount)?);
+    }
 
-    fn assert_gen_err<T: Any>(result: StdResult<T>, err_str_includes: &str) -> bool {
-        match result {
-            Ok(_) => panic!("An error was expected, but no error could be extracted"),
-            Err(err) => match err {
-                StdError::GenericErr { msg, .. } => {
-                    msg.contains(err_str_includes)
-                },
-                _ => panic!("Unexpected result"),
-            },
-        }
+    Ok(response)
+}
+
+/// Returns the sender together with its decoys, in address order, so the order in which their
+/// storage is accessed does not reveal which one is the sender
+fn with_decoys(sender: &Addr, decoys: Option<Vec<Addr>>) -> Vec<Addr> {
+    let mut accounts = decoys.unwrap_or_default();
+    accounts.push(sender.clone());
+    accounts.sort();
+    accounts.dedup();
+    accounts
+}
+
+/// Sets `Outcome.richest` to the head of the round's ranking, or resets it if nobody is left
+fn recompute_richest(storage: &mut dyn Storage, round_id: u64) -> StdResult<()> {
+    let mut outcome = OutcomeStore::load(storage, round_id)?;
+    match RankingStore::first(storage, round_id)? {
+        Some(richest) => outcome.update_richest(richest.addr, richest.networth),
+        None => outcome = Outcome::init(),
     }
 
-    #[test]
-    fn test_init_sanity() {
-        let (res, deps) = init_helper();
+    OutcomeStore::save(storage, round_id, &outcome)
+}
 
-        // we can call .unwrap() to assert this was a success
-        assert_eq!(0, res.unwrap().messages.len());
+pub fn try_set_winner_reveal(
+    deps: DepsMut,
+    info: MessageInfo,
+    round_id: u64,
+    reveal_networth: bool,
+) -> Result<Response, ContractError> {
+    load_round(deps.as_ref(), round_id)?;
+    WinnerRevealStore::save(deps.storage, round_id, &info.sender, reveal_networth)?;
 
-        let state = state_read(deps.as_ref().storage).load().unwrap();
-        assert_eq!(state, Outcome { richest: Millionaire { addr: Addr::unchecked(""), networth: Uint128::zero() } });
+    let answer = HandleAnswer::SetWinnerReveal { status: ResponseStatus::Success };
+    Ok(Response::new().set_data(to_binary(&answer)?))
+}
+
+/// Closes a round for good, after which its winner can be queried publicly.
+/// Can be called by anyone once the round's deadline has passed
+pub fn try_finalize_round(
+    deps: DepsMut,
+    env: Env,
+    round_id: u64,
+) -> Result<Response, ContractError> {
+    let mut round = load_round(deps.as_ref(), round_id)?;
+    match round.status(&env.block) {
+        RoundStatus::Open => return Err(ContractError::RoundStillOpen { round_id }),
+        RoundStatus::Finalized => return Err(ContractError::RoundAlreadyFinalized { round_id }),
+        RoundStatus::Cancelled => return Err(ContractError::RoundCancelled { round_id }),
+        RoundStatus::Closing => (),
+    }
+    // the winner is only known once commitments can no longer be revealed
+    if matches!(RevealDeadlineStore::may_load(deps.storage, round_id)?, Some(deadline) if env.block.height < deadline) {
+        return Err(ContractError::RoundStillOpen { round_id });
     }
 
-    #[test]
-    fn test_richest_sanity() {
-        let (_, mut deps) = init_helper();
-        submit_networth_helper(&mut deps, vec![("alice", 1), ("bob", 2)]);
+    round.finalized = true;
+    RoundStore::save(deps.storage, round_id, &round)?;
 
-        let alice_query_res = query_all_info(deps.as_ref(), Addr::unchecked("alice")).unwrap();
-        let bob_query_res = query_all_info(deps.as_ref(), Addr::unchecked("bob")).unwrap();
+    Ok(Response::new())
+}
 
-        match alice_query_res {
-            QueryAnswer::AllInfo { richest, networth } => {
-                assert_eq!(richest, false); assert_eq!(networth, Uint128::one());       
-            },
-            res => panic!("unexpected QueryAnswer type: {res:?}"),
-        }
-        match bob_query_res {
-            QueryAnswer::AllInfo { richest, networth } => {
-                assert_eq!(richest, true); assert_eq!(networth, Uint128::from(2u128));       
-            },
-            res => panic!("unexpected QueryAnswer type: {res:?}"),
-        }
+/// Sets the deadline of a round that has none to the current block, which closes it for submissions
+pub fn try_close_round(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    round_id: u64,
+) -> Result<Response, ContractError> {
+    let mut round = load_round(deps.as_ref(), round_id)?;
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != round.creator && info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    if round.end_height.is_some() || round.end_time.is_some() {
+        return Err(StdError::generic_err("Only rounds without a deadline can be closed").into());
+    }
+    if round.status(&env.block) != RoundStatus::Open {
+        return Err(ContractError::RoundClosed { round_id });
     }
 
-    #[test]
-    fn test_richest_logic() {
-        let (_, mut deps) = init_helper();
-        submit_networth_helper(&mut deps, vec![("alice", 1), ("bob", 2)]);
+    round.end_height = Some(env.block.height);
+    RoundStore::save(deps.storage, round_id, &round)?;
 
-        assert_info_vec(deps.as_ref(), vec![
-            ("alice", false, 1), ("bob", true, 2)
-        ]);
+    Ok(Response::new())
+}
 
-        // cannot resubmit
-        let msg = ExecuteMsg::SubmitNetWorth { networth: Uint128::from(3u128) };
+pub fn try_cancel_round(
+    deps: DepsMut,
+    info: MessageInfo,
+    round_id: u64,
+) -> Result<Response, ContractError> {
+    let mut round = load_round(deps.as_ref(), round_id)?;
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != round.creator && info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    if round.finalized {
+        return Err(ContractError::RoundAlreadyFinalized { round_id });
+    }
+    if round.cancelled {
+        return Err(ContractError::RoundCancelled { round_id });
+    }
+
+    round.cancelled = true;
+    RoundStore::save(deps.storage, round_id, &round)?;
+
+    Ok(Response::new())
+}
+
+/// Sends the entry fees collected in a finalized round to its richest participant
+pub fn try_claim_prize(
+    deps: DepsMut,
+    round_id: u64,
+) -> Result<Response, ContractError> {
+    let round = load_round(deps.as_ref(), round_id)?;
+    if !round.finalized {
+        return Err(ContractError::RoundNotFinalized { round_id });
+    }
+    let pool = PrizePoolStore::load(deps.storage, round_id)?;
+    let fee = match round.entry_fee {
+        Some(fee) if !pool.is_zero() => fee,
+        _ => return Err(ContractError::NoPrize { round_id }),
+    };
+
+    // a round whose participants all withdrew has no winner, and its pool is swept instead
+    let outcome = OutcomeStore::load(deps.storage, round_id)?;
+    if outcome.richest.addr.as_str() == "" {
+        return Err(ContractError::NoPrize { round_id });
+    }
+
+    PrizePoolStore::save(deps.storage, round_id, Uint128::zero())?;
+    let prize = BankMsg::Send {
+        to_address: outcome.richest.addr.into_string(),
+        amount: vec![Coin { denom: fee.denom, amount: pool }],
+    };
+
+    Ok(Response::new().add_message(prize))
+}
+
+/// Sends the entry fees that would otherwise stay in a round's prize pool forever to its creator or
+/// the admin. In a cancelled round, those are the fees forfeited by participants who withdrew while
+/// it was open, as the others can still get theirs back. A finalized round without a winner has no
+/// one to claim its pool
+pub fn try_sweep_prize_pool(
+    deps: DepsMut,
+    info: MessageInfo,
+    round_id: u64,
+) -> Result<Response, ContractError> {
+    let round = load_round(deps.as_ref(), round_id)?;
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != round.creator && info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    let fee = match round.entry_fee {
+        Some(fee) => fee,
+        None => return Err(ContractError::NoPrize { round_id }),
+    };
+
+    // what remains in the pool once swept
+    let refundable = if round.cancelled {
+        let participants = ParticipantCountStore::load(deps.storage, round_id)?;
+        fee.amount.checked_mul(Uint128::from(participants)).map_err(StdError::from)?
+    } else if round.finalized {
+        if OutcomeStore::load(deps.storage, round_id)?.richest.addr.as_str() != "" {
+            return Err(ContractError::NoPrize { round_id });
+        }
+        Uint128::zero()
+    } else {
+        return Err(ContractError::RoundNotFinalized { round_id });
+    };
+    let pool = PrizePoolStore::load(deps.storage, round_id)?;
+    let swept = pool.checked_sub(refundable).map_err(StdError::from)?;
+    if swept.is_zero() {
+        return Err(ContractError::NoPrize { round_id });
+    }
+
+    PrizePoolStore::save(deps.storage, round_id, refundable)?;
+    let sweep = BankMsg::Send {
+        to_address: info.sender.into_string(),
+        amount: vec![Coin { denom: fee.denom, amount: swept }],
+    };
+
+    Ok(Response::new().add_message(sweep))
+}
+
+pub fn try_set_key(
+    deps: DepsMut,
+    info: MessageInfo,
+    key: String,
+    decoys: Option<Vec<Addr>>,
+) -> Result<Response, ContractError> {
+    for account in with_decoys(&info.sender, decoys) {
+        // the current key is read for the sender as well, so it is accessed like the decoys
+        let mut vk_store = PrefixedStorage::new(deps.storage, ViewingKey::STORAGE_KEY);
+        let hashed_key = vk_store.get(account.as_bytes());
+        if account == info.sender {
+            ViewingKey::set(deps.storage, info.sender.as_str(), key.as_str());
+        } else {
+            // decoys without a key are written as well, so they cannot be told apart from those with one
+            vk_store.set(account.as_bytes(), &hashed_key.unwrap_or_else(|| NO_VIEWING_KEY.to_vec()));
+        }
+    }
+    let answer = HandleAnswer::SetViewingKey { status: ResponseStatus::Success };
+    Ok(Response::new().set_data(to_binary(&answer)?))
+}
+
+pub fn try_create_key(deps: DepsMut, env: Env, info: MessageInfo, entropy: String) -> Result<Response, ContractError> {
+    let key = ViewingKey::create(deps.storage, &info, &env, info.sender.as_str(), entropy.as_bytes());
+    Ok(Response::new().set_data(to_binary(&HandleAnswer::CreateViewingKey { key })?))
+}
+
+pub fn try_revoke_permit(deps: DepsMut, info: MessageInfo, permit_name: String) -> Result<Response, ContractError> {
+    RevokedPermits::revoke_permit(deps.storage, PREFIX_REVOKED_PERMITS, info.sender.as_str(), &permit_name);
+    let answer = HandleAnswer::RevokePermit { status: ResponseStatus::Success };
+    Ok(Response::new().set_data(to_binary(&answer)?))
+}
+
+pub fn try_change_admin(deps: DepsMut, info: MessageInfo, address: String) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.pending_admin = Some(deps.api.addr_validate(&address)?);
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new())
+}
+
+pub fn try_accept_admin(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if config.pending_admin.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.admin = info.sender;
+    config.pending_admin = None;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new())
+}
+
+pub fn try_set_contract_status(
+    deps: DepsMut,
+    info: MessageInfo,
+    level: ContractStatusLevel,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    CONTRACT_STATUS.save(deps.storage, &level)?;
+
+    Ok(Response::new())
+}
+
+fn query_all_info(
+    deps: Deps,
+    round_id: u64,
+    addr: Addr,
+) -> Result<QueryAnswer, ContractError> {
+    let outcome = OutcomeStore::load(deps.storage, round_id)?;
+    let richest = outcome.richest.addr == addr;
+    let may_networth = NetWorthStore::may_load(deps.storage, round_id, &addr)?;
+    let networth = match may_networth {
+        Some(x) => x,
+        None => return Err(ContractError::NotSubmitted {}),
+    };
+
+    let resp = QueryAnswer::AllInfo { 
+        richest,
+        networth,
+        snapshot_height: SnapshotHeightStore::may_load(deps.storage, round_id, &addr)?,
+    };
+        
+    Ok(resp)
+}
+
+fn query_richest(
+    deps: Deps,
+    round_id: u64,
+    addr: Addr,
+) -> Result<QueryAnswer, ContractError> {
+    let outcome = OutcomeStore::load(deps.storage, round_id)?;
+    let richest = outcome.richest.addr == addr;
+
+    let resp = QueryAnswer::AmIRichest {
+        richest,
+    };
+        
+    Ok(resp)
+}
+
+/// Returns the rank of `addr` in the round, and the number of participants
+fn load_rank(
+    deps: Deps,
+    round_id: u64,
+    addr: &Addr,
+) -> Result<(u32, u32), ContractError> {
+    let networth = match NetWorthStore::may_load(deps.storage, round_id, addr)? {
+        Some(x) => x,
+        None => return Err(ContractError::NotSubmitted {}),
+    };
+    let rank = RankingStore::rank_of(deps.storage, round_id, networth)?;
+
+    Ok((rank, RankingStore::len(deps.storage, round_id)?))
+}
+
+fn query_my_rank(
+    deps: Deps,
+    round_id: u64,
+    addr: Addr,
+) -> Result<QueryAnswer, ContractError> {
+    let (rank, participants) = load_rank(deps, round_id, &addr)?;
+
+    let resp = QueryAnswer::MyRank {
+        rank,
+        participants,
+    };
+
+    Ok(resp)
+}
+
+fn query_my_percentile(
+    deps: Deps,
+    round_id: u64,
+    addr: Addr,
+) -> Result<QueryAnswer, ContractError> {
+    let (rank, participants) = load_rank(deps, round_id, &addr)?;
+    let config = CONFIG.load(deps.storage)?;
+
+    let resp = QueryAnswer::MyPercentile {
+        top_percent: percentile_bucket(&config.percentile_buckets, rank, participants),
+    };
+
+    Ok(resp)
+}
+
+fn load_round(deps: Deps, round_id: u64) -> Result<Round, ContractError> {
+    match RoundStore::may_load(deps.storage, round_id)? {
+        Some(round) => Ok(round),
+        None => Err(ContractError::RoundNotFound { round_id }),
+    }
+}
+
+/// Loads a round that still accepts submissions
+fn load_open_round(deps: Deps, env: &Env, round_id: u64) -> Result<Round, ContractError> {
+    let round = load_round(deps, round_id)?;
+    if round.status(&env.block) != RoundStatus::Open {
+        return Err(ContractError::RoundClosed { round_id });
+    }
+    Ok(round)
+}
+
+fn query_contest_info(
+    deps: Deps,
+    env: Env,
+    round_id: u64,
+) -> Result<QueryAnswer, ContractError> {
+    let round = load_round(deps, round_id)?;
+
+    let resp = QueryAnswer::ContestInfo {
+        participants: ParticipantCountStore::load(deps.storage, round_id)?,
+        status: round.status(&env.block),
+        created_height: RoundStore::load_creation_height(deps.storage, round_id)?,
+        entry_fee: round.entry_fee,
+        prize_pool: PrizePoolStore::load(deps.storage, round_id)?,
+        verified_denoms: VerifiedDenomsStore::may_load(deps.storage, round_id)?,
+        include_delegations: IncludeDelegationsStore::load(deps.storage, round_id)?,
+        escrow_token: EscrowTokenStore::may_load(deps.storage, round_id)?,
+        reveal_deadline: RevealDeadlineStore::may_load(deps.storage, round_id)?,
+    };
+
+    Ok(resp)
+}
+
+fn query_round_status(
+    deps: Deps,
+    env: Env,
+    round_id: u64,
+) -> Result<QueryAnswer, ContractError> {
+    let round = load_round(deps, round_id)?;
+
+    let resp = QueryAnswer::RoundStatus {
+        status: round.status(&env.block),
+        end_height: round.end_height,
+        end_time: round.end_time,
+    };
+
+    Ok(resp)
+}
+
+fn query_config(deps: Deps) -> Result<QueryAnswer, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    let resp = QueryAnswer::Config {
+        admin: config.admin,
+        pending_admin: config.pending_admin,
+        percentile_buckets: config.percentile_buckets,
+    };
+
+    Ok(resp)
+}
+
+fn query_contract_status(deps: Deps) -> Result<QueryAnswer, ContractError> {
+    let resp = QueryAnswer::ContractStatus {
+        status: CONTRACT_STATUS.load(deps.storage)?,
+    };
+
+    Ok(resp)
+}
+
+fn query_winner(
+    deps: Deps,
+    round_id: u64,
+) -> Result<QueryAnswer, ContractError> {
+    let round = load_round(deps, round_id)?;
+    if !round.finalized {
+        return Err(ContractError::RoundNotFinalized { round_id });
+    }
+
+    let outcome = OutcomeStore::load(deps.storage, round_id)?;
+    let resp = match NetWorthStore::may_load(deps.storage, round_id, &outcome.richest.addr)? {
+        // nobody submitted in this round
+        None => QueryAnswer::Winner { winner: None, networth: None },
+        Some(_) => {
+            let reveal = WinnerRevealStore::load(deps.storage, round_id, &outcome.richest.addr)?;
+            QueryAnswer::Winner {
+                networth: if reveal { Some(outcome.richest.networth) } else { None },
+                winner: Some(outcome.richest.addr),
+            }
+        },
+    };
+
+    Ok(resp)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+    use std::cell::RefCell;
+
+    use crate::state::{NETWORTHS, PREFIX_DEPOSITS, PREFIX_NETWORTHS};
+
+    use super::*;
+
+    use cosmwasm_std::testing::{
+        mock_env, mock_info, mock_dependencies,
+        MockStorage, MockApi, MockQuerier
+    };
+    use cosmwasm_std::{coin, coins, FullDelegation, OwnedDeps, from_slice, Order, QuerierWrapper, Record};
+
+    type StorageTrace = Vec<(&'static str, Vec<u8>, usize)>;
+
+    /// Wraps a storage to record every access as `(operation, key, value length)`
+    struct TracingStorage<'a> {
+        inner: &'a mut dyn Storage,
+        trace: RefCell<StorageTrace>,
+    }
+
+    impl<'a> TracingStorage<'a> {
+        fn new(inner: &'a mut dyn Storage) -> Self {
+            Self { inner, trace: RefCell::new(vec![]) }
+        }
+    }
+
+    fn count_ops(trace: &StorageTrace, op: &str) -> usize {
+        trace.iter().filter(|(o, ..)| *o == op).count()
+    }
+
+    /// The accesses to keys containing `account`, with `account` cut out of the keys so the accesses
+    /// of different accounts can be compared. Only the lengths of written values are kept, as reads
+    /// return whatever was written before
+    fn account_ops(trace: &StorageTrace, account: &str) -> StorageTrace {
+        let account = account.as_bytes();
+        trace.iter()
+            .filter_map(|(op, key, len)| {
+                let pos = key.windows(account.len()).position(|window| window == account)?;
+                let key = [&key[..pos], &key[pos + account.len()..]].concat();
+                Some((*op, key, if *op == "write" { *len } else { 0 }))
+            })
+            .collect()
+    }
+
+    impl Storage for TracingStorage<'_> {
+        fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+            let value = self.inner.get(key);
+            let len = value.as_ref().map(Vec::len).unwrap_or_default();
+            self.trace.borrow_mut().push(("read", key.to_vec(), len));
+            value
+        }
+
+        fn range<'b>(
+            &'b self,
+            start: Option<&[u8]>,
+            end: Option<&[u8]>,
+            order: Order,
+        ) -> Box<dyn Iterator<Item = Record> + 'b> {
+            self.trace.borrow_mut().push(("range", start.unwrap_or_default().to_vec(), 0));
+            self.inner.range(start, end, order)
+        }
+
+        fn set(&mut self, key: &[u8], value: &[u8]) {
+            self.trace.borrow_mut().push(("write", key.to_vec(), value.len()));
+            self.inner.set(key, value)
+        }
+
+        fn remove(&mut self, key: &[u8]) {
+            self.trace.borrow_mut().push(("remove", key.to_vec(), 0));
+            self.inner.remove(key)
+        }
+    }
+
+    fn init_helper() -> (
+        StdResult<Response>, 
+        OwnedDeps<MockStorage, MockApi, MockQuerier>,
+    ) {
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg { admin: None, percentile_buckets: None, prng_seed: Binary::from(b"seed") };
+        let info = mock_info("creator", &coins(0, "coins"));
+        let res = instantiate(deps.as_mut(), mock_env(), info, msg);
+
+        (res, deps)
+    }
+
+    fn submit_networth_helper(
+        deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+        round_id: u64,
+        submissions: Vec<(&str, u128)>
+    ) -> Vec<Response>  {
+        let mut res_vec = vec![];
+        for (sender, networth) in submissions {
+            let msg = ExecuteMsg::SubmitNetWorth { round_id, networth: Uint128::from(networth), decoys: None, padding: None };
+            let info = mock_info(sender, &[]);
+            let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+            res_vec.push(res);
+        }
+        res_vec
+    }
+
+    /// Fields of `ExecuteMsg::CreateRound`, so tests only spell out those they set
+    #[derive(Default)]
+    struct RoundParams {
+        end_height: Option<u64>,
+        end_time: Option<u64>,
+        entry_fee: Option<Coin>,
+        verified_denoms: Option<Vec<String>>,
+        include_delegations: Option<bool>,
+        escrow_token: Option<Snip20Token>,
+        reveal_deadline: Option<u64>,
+    }
+
+    fn create_round_msg(params: RoundParams) -> ExecuteMsg {
+        ExecuteMsg::CreateRound {
+            end_height: params.end_height,
+            end_time: params.end_time,
+            entry_fee: params.entry_fee,
+            verified_denoms: params.verified_denoms,
+            include_delegations: params.include_delegations,
+            escrow_token: params.escrow_token,
+            reveal_deadline: params.reveal_deadline,
+            padding: None,
+        }
+    }
+
+    /// Creates a round as `creator` and returns its id
+    fn create_round_helper(
+        deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+        creator: &str,
+        params: RoundParams,
+    ) -> u64 {
+        let res = execute(deps.as_mut(), mock_env(), mock_info(creator, &[]), create_round_msg(params)).unwrap();
+        let round_id = res.attributes.iter().find(|attr| attr.key == "round_id").unwrap();
+        round_id.value.parse().unwrap()
+    }
+
+    fn assert_info(deps: Deps, round_id: u64, acc: &str, exp_richest: bool, exp_networth: u128) {
+        let res = query_all_info(deps, round_id, Addr::unchecked(acc)).unwrap();
+
+        match res {
+            QueryAnswer::AllInfo { richest, networth, .. } => {
+                assert_eq!(richest, exp_richest); assert_eq!(networth, Uint128::from(exp_networth));       
+            },
+            res => panic!("unexpected QueryAnswer type: {res:?}"),
+        }
+    }
+
+    fn assert_info_vec(deps: Deps, round_id: u64, acc_richest_networth: Vec<(&str, bool, u128)>) {
+        for (acc, exp_richest, exp_networth) in acc_richest_networth {
+            assert_info(deps, round_id, acc, exp_richest, exp_networth);
+        }
+    }
+
+    fn assert_gen_err<T: Any, E>(result: Result<T, E>, err_str_includes: &str) -> bool
+    where
+        ContractError: From<E>,
+    {
+        match result {
+            Ok(_) => panic!("An error was expected, but no error could be extracted"),
+            Err(err) => match ContractError::from(err) {
+                ContractError::Std(StdError::GenericErr { msg, .. }) => {
+                    msg.contains(err_str_includes)
+                },
+                _ => panic!("Unexpected result"),
+            },
+        }
+    }
+
+    #[test]
+    fn test_init_sanity() {
+        let (res, deps) = init_helper();
+
+        // we can call .unwrap() to assert this was a success
+        assert_eq!(0, res.unwrap().messages.len());
+
+        let state = OutcomeStore::load(deps.as_ref().storage, 0).unwrap();
+        assert_eq!(state, Outcome { richest: Millionaire { addr: Addr::unchecked(""), networth: Uint128::zero() } });
+    }
+
+    #[test]
+    fn test_richest_sanity() {
+        let (_, mut deps) = init_helper();
+        submit_networth_helper(&mut deps, 0, vec![("alice", 1), ("bob", 2)]);
+
+        let alice_query_res = query_all_info(deps.as_ref(), 0, Addr::unchecked("alice")).unwrap();
+        let bob_query_res = query_all_info(deps.as_ref(), 0, Addr::unchecked("bob")).unwrap();
+
+        match alice_query_res {
+            QueryAnswer::AllInfo { richest, networth, .. } => {
+                assert_eq!(richest, false); assert_eq!(networth, Uint128::one());       
+            },
+            res => panic!("unexpected QueryAnswer type: {res:?}"),
+        }
+        match bob_query_res {
+            QueryAnswer::AllInfo { richest, networth, .. } => {
+                assert_eq!(richest, true); assert_eq!(networth, Uint128::from(2u128));       
+            },
+            res => panic!("unexpected QueryAnswer type: {res:?}"),
+        }
+    }
+
+    #[test]
+    fn test_richest_logic() {
+        let (_, mut deps) = init_helper();
+        submit_networth_helper(&mut deps, 0, vec![("alice", 1), ("bob", 2)]);
+
+        assert_info_vec(deps.as_ref(), 0, vec![
+            ("alice", false, 1), ("bob", true, 2)
+        ]);
+
+        // cannot resubmit
+        let msg = ExecuteMsg::SubmitNetWorth { round_id: 0, networth: Uint128::from(3u128), decoys: None, padding: None };
         let info = mock_info("alice", &[]);
         let res = execute(deps.as_mut(), mock_env(), info, msg);
         match res {
@@ -275,91 +1427,1450 @@ mod tests {
                 ContractError::AlreadySubmittedNetworth { networth } => {
                     assert_eq!(networth, Uint128::one())
                 },
+                err => panic!("unexpected ContractError: {err:?}"),
             },
         }
 
-        // tied networth => previous `richest` remains the same
-        submit_networth_helper(&mut deps, vec![("carol", 2)]);
-        assert_info_vec(deps.as_ref(), vec![
-            ("alice", false, 1), ("bob", true, 2), ("carol", false, 2)
+        // tied networth => previous `richest` remains the same
+        submit_networth_helper(&mut deps, 0, vec![("carol", 2)]);
+        assert_info_vec(deps.as_ref(), 0, vec![
+            ("alice", false, 1), ("bob", true, 2), ("carol", false, 2)
+        ]);
+
+        // new richest
+        submit_networth_helper(&mut deps, 0, vec![("dan", 3)]);
+        assert_info_vec(deps.as_ref(), 0, vec![
+            ("alice", false, 1), ("bob", false, 2), ("carol", false, 2), ("dan", true, 3)
+        ]);
+
+        // new but not richest
+        submit_networth_helper(&mut deps, 0, vec![("eve", 2)]);
+        assert_info_vec(deps.as_ref(), 0, vec![
+            ("alice", false, 1), ("bob", false, 2), ("carol", false, 2), ("dan", true, 3), ("eve", false, 2)
+        ]);
+
+        // executing set viewing key does not change state on submissions
+        let setvk_msg = ExecuteMsg::SetViewingKey { key: "vka".to_string(), decoys: None, padding: None };
+        let info = mock_info("alice", &[]);
+        execute(deps.as_mut(), mock_env(), info, setvk_msg).unwrap();
+        assert_info_vec(deps.as_ref(), 0, vec![
+            ("alice", false, 1), ("bob", false, 2), ("carol", false, 2), ("dan", true, 3), ("eve", false, 2)
+        ]);
+    }
+
+    #[test]
+    fn test_vk_query() {
+        let (_, mut deps) = init_helper();
+        submit_networth_helper(&mut deps, 0, vec![("alice", 1), ("bob", 2)]);
+
+        // no vk set yet ----------------------
+        // AllInfo
+        let q_msg_all = QueryMsg::AllInfo { round_id: 0, addr: Addr::unchecked("alice"), key: "vka".to_string(), padding: None };
+        let query_result = query(deps.as_ref(), mock_env(), q_msg_all.clone());
+        assert_eq!(query_result.unwrap_err(), ContractError::Unauthorized {});
+
+        // AmIRichest
+        let q_msg_richest = QueryMsg::AmIRichest { round_id: 0, addr: Addr::unchecked("alice"), key: "vka".to_string(), padding: None };
+        let query_result = query(deps.as_ref(), mock_env(), q_msg_richest.clone());
+        assert_eq!(query_result.unwrap_err(), ContractError::Unauthorized {});
+
+        // set vk ----------------------
+        let setvk_msg = ExecuteMsg::SetViewingKey { key: "vka".to_string(), decoys: None, padding: None };
+        let info = mock_info("alice", &[]);
+        execute(deps.as_mut(), mock_env(), info, setvk_msg).unwrap();
+
+        // can view result with correct vk ----------------------
+        // AllInfo
+        let query_result = query(deps.as_ref(), mock_env(), q_msg_all);
+        assert!(query_result.is_ok());
+        let query_answer = from_binary::<QueryAnswer>(&query_result.unwrap()).unwrap();
+        assert_eq!(query_answer, QueryAnswer::AllInfo { richest: false, networth: Uint128::one(), snapshot_height: None });
+
+        // AmIRichest
+        let query_result = query(deps.as_ref(), mock_env(), q_msg_richest);
+        assert!(query_result.is_ok());
+        let query_answer = from_binary::<QueryAnswer>(&query_result.unwrap()).unwrap();
+        assert_eq!(query_answer, QueryAnswer::AmIRichest { richest: false });
+
+        // cannot view result with wrong vk ----------------------
+        // AllInfo
+        let q_msg_wrong_vk_all = QueryMsg::AllInfo { round_id: 0, addr: Addr::unchecked("alice"), key: "vk_wrong".to_string(), padding: None };
+        let query_result = query(deps.as_ref(), mock_env(), q_msg_wrong_vk_all);
+        assert_eq!(query_result.unwrap_err(), ContractError::Unauthorized {});
+
+        // AmIRichest
+        let q_msg_wrong_vk_richest = QueryMsg::AmIRichest { round_id: 0, addr: Addr::unchecked("alice"), key: "vk_wrong".to_string(), padding: None };
+        let query_result = query(deps.as_ref(), mock_env(), q_msg_wrong_vk_richest);
+        assert_eq!(query_result.unwrap_err(), ContractError::Unauthorized {});
+
+        // cannot view result with "wrong address" ----------------------
+        // AllInfo
+        let q_msg_wrong_addr_all = QueryMsg::AllInfo { round_id: 0, addr: Addr::unchecked("bob"), key: "vka".to_string(), padding: None };
+        let query_result = query(deps.as_ref(), mock_env(), q_msg_wrong_addr_all);
+        assert_eq!(query_result.unwrap_err(), ContractError::Unauthorized {});
+        
+        // AmIRichest
+        let q_msg_wrong_addr_richest = QueryMsg::AmIRichest { round_id: 0, addr: Addr::unchecked("bob"), key: "vka".to_string(), padding: None };
+        let query_result = query(deps.as_ref(), mock_env(), q_msg_wrong_addr_richest);
+        assert_eq!(query_result.unwrap_err(), ContractError::Unauthorized {});
+        
+    }
+
+    #[test]
+    fn test_rounds_are_independent() {
+        let (_, mut deps) = init_helper();
+
+        let info = mock_info("creator", &[]);
+        let msg = create_round_msg(RoundParams::default());
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(res.attributes[0].value, "1");
+
+        submit_networth_helper(&mut deps, 0, vec![("alice", 1), ("bob", 2)]);
+        // alice can submit again in a different round
+        submit_networth_helper(&mut deps, 1, vec![("alice", 3), ("carol", 2)]);
+
+        assert_info_vec(deps.as_ref(), 0, vec![
+            ("alice", false, 1), ("bob", true, 2)
+        ]);
+        assert_info_vec(deps.as_ref(), 1, vec![
+            ("alice", true, 3), ("carol", false, 2)
+        ]);
+
+        // bob has not submitted in round 1
+        let query_result = query_all_info(deps.as_ref(), 1, Addr::unchecked("bob"));
+        assert_eq!(query_result.unwrap_err(), ContractError::NotSubmitted {});
+
+        // cannot submit to a round that has not been created
+        let msg = ExecuteMsg::SubmitNetWorth { round_id: 2, networth: Uint128::one(), decoys: None, padding: None };
+        let info = mock_info("alice", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, msg);
+        assert_eq!(res.unwrap_err(), ContractError::RoundNotFound { round_id: 2 });
+    }
+
+    #[test]
+    fn test_round_deadlines() {
+        let (_, mut deps) = init_helper();
+        let env = mock_env();
+
+        // round 1 closes by height, round 2 closes by time
+        let info = mock_info("creator", &[]);
+        let msg = create_round_msg(RoundParams { end_height: Some(env.block.height + 10), ..Default::default() });
+        execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let msg = create_round_msg(RoundParams { end_time: Some(env.block.time.seconds() + 60), ..Default::default() });
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let assert_status = |deps: Deps, env: Env, round_id: u64, exp_status: RoundStatus| {
+            let res = query(deps, env, QueryMsg::RoundStatus { round_id, padding: None }).unwrap();
+            match from_binary::<QueryAnswer>(&res).unwrap() {
+                QueryAnswer::RoundStatus { status, .. } => assert_eq!(status, exp_status),
+                res => panic!("unexpected QueryAnswer type: {res:?}"),
+            }
+        };
+        for round_id in 0..3 {
+            assert_status(deps.as_ref(), env.clone(), round_id, RoundStatus::Open);
+        }
+
+        // submissions before the deadline are accepted
+        submit_networth_helper(&mut deps, 1, vec![("alice", 1)]);
+        submit_networth_helper(&mut deps, 2, vec![("alice", 1)]);
+
+        // reaching the end height closes round 1 only
+        let mut env_later = mock_env();
+        env_later.block.height += 10;
+        assert_status(deps.as_ref(), env_later.clone(), 0, RoundStatus::Open);
+        assert_status(deps.as_ref(), env_later.clone(), 1, RoundStatus::Closing);
+        assert_status(deps.as_ref(), env_later.clone(), 2, RoundStatus::Open);
+
+        let msg = ExecuteMsg::SubmitNetWorth { round_id: 1, networth: Uint128::from(2u128), decoys: None, padding: None };
+        let res = execute(deps.as_mut(), env_later.clone(), mock_info("bob", &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::RoundClosed { round_id: 1 });
+
+        // reaching the end time closes round 2
+        env_later.block.time = env_later.block.time.plus_seconds(60);
+        assert_status(deps.as_ref(), env_later.clone(), 2, RoundStatus::Closing);
+
+        let msg = ExecuteMsg::SubmitNetWorth { round_id: 2, networth: Uint128::from(2u128), decoys: None, padding: None };
+        let res = execute(deps.as_mut(), env_later.clone(), mock_info("bob", &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::RoundClosed { round_id: 2 });
+
+        // round 0 has no deadline
+        let msg = ExecuteMsg::SubmitNetWorth { round_id: 0, networth: Uint128::from(2u128), decoys: None, padding: None };
+        execute(deps.as_mut(), env_later, mock_info("bob", &[]), msg).unwrap();
+    }
+
+    #[test]
+    fn test_finalize_and_winner() {
+        let (_, mut deps) = init_helper();
+        let env = mock_env();
+
+        create_round_helper(&mut deps, "creator", RoundParams {
+            end_height: Some(env.block.height + 10), ..Default::default()
+        });
+        create_round_helper(&mut deps, "creator", RoundParams {
+            end_height: Some(env.block.height + 10), ..Default::default()
+        });
+
+        submit_networth_helper(&mut deps, 1, vec![("alice", 1), ("bob", 2)]);
+
+        // cannot finalize, nor see the winner, before the deadline
+        let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::FinalizeRound { round_id: 1, padding: None });
+        assert_eq!(res.unwrap_err(), ContractError::RoundStillOpen { round_id: 1 });
+        let query_result = query(deps.as_ref(), env.clone(), QueryMsg::Winner { round_id: 1, padding: None });
+        assert_eq!(query_result.unwrap_err(), ContractError::RoundNotFinalized { round_id: 1 });
+
+        let mut env_later = mock_env();
+        env_later.block.height += 10;
+        for round_id in 1..3 {
+            let msg = ExecuteMsg::FinalizeRound { round_id, padding: None };
+            execute(deps.as_mut(), env_later.clone(), mock_info("anyone", &[]), msg).unwrap();
+        }
+        let res = execute(deps.as_mut(), env_later.clone(), mock_info("anyone", &[]), ExecuteMsg::FinalizeRound { round_id: 1, padding: None });
+        assert_eq!(res.unwrap_err(), ContractError::RoundAlreadyFinalized { round_id: 1 });
+
+        // winner is public, but networth is hidden by default
+        let res = query(deps.as_ref(), env_later.clone(), QueryMsg::Winner { round_id: 1, padding: None }).unwrap();
+        assert_eq!(
+            from_binary::<QueryAnswer>(&res).unwrap(),
+            QueryAnswer::Winner { winner: Some(Addr::unchecked("bob")), networth: None }
+        );
+
+        // winner opts in to reveal their networth
+        let msg = ExecuteMsg::SetWinnerReveal { round_id: 1, reveal_networth: true, padding: None };
+        execute(deps.as_mut(), env_later.clone(), mock_info("bob", &[]), msg).unwrap();
+        let res = query(deps.as_ref(), env_later.clone(), QueryMsg::Winner { round_id: 1, padding: None }).unwrap();
+        assert_eq!(
+            from_binary::<QueryAnswer>(&res).unwrap(),
+            QueryAnswer::Winner { winner: Some(Addr::unchecked("bob")), networth: Some(Uint128::from(2u128)) }
+        );
+
+        // a round nobody submitted to has no winner
+        let res = query(deps.as_ref(), env_later, QueryMsg::Winner { round_id: 2, padding: None }).unwrap();
+        assert_eq!(
+            from_binary::<QueryAnswer>(&res).unwrap(),
+            QueryAnswer::Winner { winner: None, networth: None }
+        );
+    }
+
+    #[test]
+    fn test_my_rank() {
+        let (_, mut deps) = init_helper();
+        submit_networth_helper(&mut deps, 0, vec![("alice", 2), ("bob", 5), ("carol", 2), ("dan", 1)]);
+
+        let assert_rank = |deps: Deps, acc: &str, exp_rank: u32| {
+            let res = query_my_rank(deps, 0, Addr::unchecked(acc)).unwrap();
+            assert_eq!(res, QueryAnswer::MyRank { rank: exp_rank, participants: 4 });
+        };
+        assert_rank(deps.as_ref(), "bob", 1);
+        // ties share the same rank
+        assert_rank(deps.as_ref(), "alice", 2);
+        assert_rank(deps.as_ref(), "carol", 2);
+        assert_rank(deps.as_ref(), "dan", 4);
+
+        // the head of the ranking is always the richest
+        let ranking = RankingStore::load(deps.as_ref().storage, 0).unwrap();
+        let outcome = OutcomeStore::load(deps.as_ref().storage, 0).unwrap();
+        assert_eq!(ranking[0], outcome.richest);
+
+        // requires a viewing key
+        let q_msg = QueryMsg::MyRank { round_id: 0, addr: Addr::unchecked("dan"), key: "vkd".to_string(), padding: None };
+        let query_result = query(deps.as_ref(), mock_env(), q_msg.clone());
+        assert_eq!(query_result.unwrap_err(), ContractError::Unauthorized {});
+
+        let setvk_msg = ExecuteMsg::SetViewingKey { key: "vkd".to_string(), decoys: None, padding: None };
+        execute(deps.as_mut(), mock_env(), mock_info("dan", &[]), setvk_msg).unwrap();
+        let query_result = query(deps.as_ref(), mock_env(), q_msg).unwrap();
+        let query_answer = from_binary::<QueryAnswer>(&query_result).unwrap();
+        assert_eq!(query_answer, QueryAnswer::MyRank { rank: 4, participants: 4 });
+
+        // non-participants have no rank
+        let query_result = query_my_rank(deps.as_ref(), 0, Addr::unchecked("eve"));
+        assert_eq!(query_result.unwrap_err(), ContractError::NotSubmitted {});
+    }
+
+    #[test]
+    fn test_my_percentile() {
+        let (_, mut deps) = init_helper();
+        // 10 participants, networth 1..=10
+        let names = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"];
+        let submissions = names.iter().zip(1..=10).map(|(name, networth)| (*name, networth)).collect();
+        submit_networth_helper(&mut deps, 0, submissions);
+
+        let assert_percentile = |deps: Deps, acc: &str, exp_top_percent: u8| {
+            let res = query_my_percentile(deps, 0, Addr::unchecked(acc)).unwrap();
+            assert_eq!(res, QueryAnswer::MyPercentile { top_percent: exp_top_percent });
+        };
+        assert_percentile(deps.as_ref(), "j", 10);
+        assert_percentile(deps.as_ref(), "i", 25);
+        assert_percentile(deps.as_ref(), "h", 50);
+        assert_percentile(deps.as_ref(), "g", 50);
+        assert_percentile(deps.as_ref(), "f", 50);
+        assert_percentile(deps.as_ref(), "e", 100);
+        assert_percentile(deps.as_ref(), "a", 100);
+
+        // custom buckets
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg { admin: None, percentile_buckets: Some(vec![50]), prng_seed: Binary::from(b"seed") };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        submit_networth_helper(&mut deps, 0, vec![("alice", 1), ("bob", 2)]);
+        assert_percentile(deps.as_ref(), "bob", 50);
+        assert_percentile(deps.as_ref(), "alice", 100);
+
+        // invalid buckets
+        for buckets in [vec![], vec![50, 25], vec![0, 10], vec![10, 101]] {
+            let mut deps = mock_dependencies();
+            let msg = InstantiateMsg { admin: None, percentile_buckets: Some(buckets), prng_seed: Binary::from(b"seed") };
+            let res = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+            assert!(assert_gen_err(res, "Percentile buckets must be ascending"));
+        }
+    }
+
+    #[test]
+    fn test_update_networth() {
+        let (_, mut deps) = init_helper();
+        submit_networth_helper(&mut deps, 0, vec![("alice", 1), ("bob", 3), ("carol", 2)]);
+
+        let update = |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, sender: &str, networth: u128| {
+            let msg = ExecuteMsg::UpdateNetWorth { round_id: 0, networth: Uint128::from(networth), padding: None };
+            execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg)
+        };
+
+        // cannot update without a previous submission
+        let res = update(&mut deps, "dan", 5);
+        assert_eq!(res.unwrap_err(), ContractError::NotSubmitted {});
+
+        // raising a networth above the richest takes over
+        update(&mut deps, "alice", 4).unwrap();
+        assert_info_vec(deps.as_ref(), 0, vec![
+            ("alice", true, 4), ("bob", false, 3), ("carol", false, 2)
         ]);
 
-        // new richest
-        submit_networth_helper(&mut deps, vec![("dan", 3)]);
-        assert_info_vec(deps.as_ref(), vec![
-            ("alice", false, 1), ("bob", false, 2), ("carol", false, 2), ("dan", true, 3)
+        // the richest lowering their networth hands over to the next richest
+        update(&mut deps, "alice", 1).unwrap();
+        assert_info_vec(deps.as_ref(), 0, vec![
+            ("alice", false, 1), ("bob", true, 3), ("carol", false, 2)
         ]);
+        let res = query_my_rank(deps.as_ref(), 0, Addr::unchecked("alice")).unwrap();
+        assert_eq!(res, QueryAnswer::MyRank { rank: 3, participants: 3 });
 
-        // new but not richest
-        submit_networth_helper(&mut deps, vec![("eve", 2)]);
-        assert_info_vec(deps.as_ref(), vec![
-            ("alice", false, 1), ("bob", false, 2), ("carol", false, 2), ("dan", true, 3), ("eve", false, 2)
+        // an update tying the richest does not take over
+        update(&mut deps, "carol", 3).unwrap();
+        assert_info_vec(deps.as_ref(), 0, vec![
+            ("alice", false, 1), ("bob", true, 3), ("carol", false, 3)
         ]);
 
-        // executing set viewing key does not change state on submissions
-        let setvk_msg = ExecuteMsg::SetViewingKey { key: "vka".to_string() };
-        let info = mock_info("alice", &[]);
-        execute(deps.as_mut(), mock_env(), info, setvk_msg).unwrap();
-        assert_info_vec(deps.as_ref(), vec![
-            ("alice", false, 1), ("bob", false, 2), ("carol", false, 2), ("dan", true, 3), ("eve", false, 2)
+        // SubmitNetWorth still rejects resubmissions
+        let msg = ExecuteMsg::SubmitNetWorth { round_id: 0, networth: Uint128::from(5u128), decoys: None, padding: None };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::AlreadySubmittedNetworth { networth: Uint128::one() });
+    }
+
+    #[test]
+    fn test_withdraw_submission() {
+        let (_, mut deps) = init_helper();
+        submit_networth_helper(&mut deps, 0, vec![("alice", 1), ("bob", 3), ("carol", 2)]);
+
+        let setvk_msg = ExecuteMsg::SetViewingKey { key: "vkb".to_string(), decoys: None, padding: None };
+        execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), setvk_msg).unwrap();
+        let msg = ExecuteMsg::SetWinnerReveal { round_id: 0, reveal_networth: true, padding: None };
+        execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg).unwrap();
+
+        // the richest leaves, so the next richest takes over
+        let msg = ExecuteMsg::WithdrawSubmission { round_id: 0, padding: None };
+        execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg.clone()).unwrap();
+        assert_info_vec(deps.as_ref(), 0, vec![
+            ("alice", false, 1), ("carol", true, 2)
         ]);
+
+        // no data is left behind for bob
+        assert_eq!(NetWorthStore::may_load(deps.as_ref().storage, 0, &Addr::unchecked("bob")).unwrap(), None);
+        assert!(!WinnerRevealStore::load(deps.as_ref().storage, 0, &Addr::unchecked("bob")).unwrap());
+        let ranking = RankingStore::load(deps.as_ref().storage, 0).unwrap();
+        assert!(ranking.iter().all(|m| m.addr != Addr::unchecked("bob")));
+        assert!(ViewingKey::check(deps.as_ref().storage, "bob", "vkb").is_err());
+
+        // cannot withdraw twice
+        let res = execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg.clone());
+        assert_eq!(res.unwrap_err(), ContractError::NotSubmitted {});
+
+        // data stored without a submission is erased as well
+        let setvk_msg = ExecuteMsg::SetViewingKey { key: "vkd".to_string(), decoys: None, padding: None };
+        execute(deps.as_mut(), mock_env(), mock_info("dave", &[]), setvk_msg).unwrap();
+        let reveal_msg = ExecuteMsg::SetWinnerReveal { round_id: 0, reveal_networth: true, padding: None };
+        execute(deps.as_mut(), mock_env(), mock_info("dave", &[]), reveal_msg).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("dave", &[]), msg.clone()).unwrap();
+        assert!(!WinnerRevealStore::load(deps.as_ref().storage, 0, &Addr::unchecked("dave")).unwrap());
+        assert!(ViewingKey::check(deps.as_ref().storage, "dave", "vkd").is_err());
+
+        // bob can rejoin
+        submit_networth_helper(&mut deps, 0, vec![("bob", 1)]);
+
+        // once everyone has left, there is no richest
+        for sender in ["alice", "bob", "carol"] {
+            execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg.clone()).unwrap();
+        }
+        let outcome = OutcomeStore::load(deps.as_ref().storage, 0).unwrap();
+        assert_eq!(outcome, Outcome::init());
+
+        // submissions are fixed once the round has ended
+        submit_networth_helper(&mut deps, 0, vec![("alice", 1)]);
+        let close_msg = ExecuteMsg::CloseRound { round_id: 0, padding: None };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), close_msg).unwrap();
+        let mut env = mock_env();
+        env.block.height += 1;
+        let res = execute(deps.as_mut(), env, mock_info("alice", &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::RoundClosed { round_id: 0 });
     }
 
     #[test]
-    fn test_vk_query() {
+    fn test_change_admin() {
         let (_, mut deps) = init_helper();
-        submit_networth_helper(&mut deps, vec![("alice", 1), ("bob", 2)]);
 
-        // no vk set yet ----------------------
-        // AllInfo
-        let q_msg_all = QueryMsg::AllInfo { addr: Addr::unchecked("alice"), key: "vka".to_string() };
-        let query_result = query(deps.as_ref(), mock_env(), q_msg_all.clone());
-        assert_gen_err(query_result, "Wrong viewing key for this address or viewing key not set");
+        let assert_admin = |deps: Deps, exp_admin: &str, exp_pending: Option<&str>| {
+            let res = query(deps, mock_env(), QueryMsg::Config { padding: None }).unwrap();
+            match from_binary::<QueryAnswer>(&res).unwrap() {
+                QueryAnswer::Config { admin, pending_admin, .. } => {
+                    assert_eq!(admin, Addr::unchecked(exp_admin));
+                    assert_eq!(pending_admin, exp_pending.map(Addr::unchecked));
+                },
+                res => panic!("unexpected QueryAnswer type: {res:?}"),
+            }
+        };
+        // admin defaults to the instantiator
+        assert_admin(deps.as_ref(), "creator", None);
 
-        // AmIRichest
-        let q_msg_richest = QueryMsg::AmIRichest { addr: Addr::unchecked("alice"), key: "vka".to_string() };
-        let query_result = query(deps.as_ref(), mock_env(), q_msg_richest.clone());
-        assert_gen_err(query_result, "Wrong viewing key for this address or viewing key not set");
+        // only the admin can propose a new admin
+        let msg = ExecuteMsg::ChangeAdmin { address: "alice".to_string(), padding: None };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg.clone());
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        assert_admin(deps.as_ref(), "creator", Some("alice"));
 
-        // set vk ----------------------
-        let setvk_msg = ExecuteMsg::SetViewingKey { key: "vka".to_string() };
-        let info = mock_info("alice", &[]);
-        execute(deps.as_mut(), mock_env(), info, setvk_msg).unwrap();
+        // only the proposed admin can accept
+        let res = execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), ExecuteMsg::AcceptAdmin { padding: None });
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), ExecuteMsg::AcceptAdmin { padding: None }).unwrap();
+        assert_admin(deps.as_ref(), "alice", None);
 
-        // can view result with correct vk ----------------------
-        // AllInfo
-        let query_result = query(deps.as_ref(), mock_env(), q_msg_all);
-        assert!(query_result.is_ok());
+        // admin can be set on instantiation
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg { admin: Some("bob".to_string()), percentile_buckets: None, prng_seed: Binary::from(b"seed") };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        assert_admin(deps.as_ref(), "bob", None);
+    }
+
+    #[test]
+    fn test_contract_status() {
+        let (_, mut deps) = init_helper();
+        submit_networth_helper(&mut deps, 0, vec![("alice", 1)]);
+
+        let set_status = |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, sender: &str, level: ContractStatusLevel| {
+            let msg = ExecuteMsg::SetContractStatus { level, padding: None };
+            execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg)
+        };
+        let assert_status = |deps: Deps, exp_status: ContractStatusLevel| {
+            let res = query(deps, mock_env(), QueryMsg::ContractStatus { padding: None }).unwrap();
+            assert_eq!(from_binary::<QueryAnswer>(&res).unwrap(), QueryAnswer::ContractStatus { status: exp_status });
+        };
+        assert_status(deps.as_ref(), ContractStatusLevel::NormalRun);
+
+        // only the admin can change the status
+        let res = set_status(&mut deps, "alice", ContractStatusLevel::StopAll);
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+        // stopping submissions
+        set_status(&mut deps, "creator", ContractStatusLevel::StopSubmissions).unwrap();
+        assert_status(deps.as_ref(), ContractStatusLevel::StopSubmissions);
+        let msg = ExecuteMsg::SubmitNetWorth { round_id: 0, networth: Uint128::one(), decoys: None, padding: None };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::ContractStopped {});
+        let msg = ExecuteMsg::UpdateNetWorth { round_id: 0, networth: Uint128::from(2u128), padding: None };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::ContractStopped {});
+        let msg = create_round_msg(RoundParams::default());
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg.clone()).unwrap();
+
+        // stopping everything but viewing keys
+        set_status(&mut deps, "creator", ContractStatusLevel::StopAll).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::ContractStopped {});
+        let msg = ExecuteMsg::WithdrawSubmission { round_id: 0, padding: None };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::ContractStopped {});
+        let setvk_msg = ExecuteMsg::SetViewingKey { key: "vka".to_string(), decoys: None, padding: None };
+        execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), setvk_msg).unwrap();
+
+        // back to normal
+        set_status(&mut deps, "creator", ContractStatusLevel::NormalRun).unwrap();
+        submit_networth_helper(&mut deps, 0, vec![("bob", 2)]);
+    }
+
+    #[test]
+    fn test_migrate_legacy() {
+        // storage as left by the original single-round contract
+        let mut deps = mock_dependencies();
+        let mut legacy_outcome = Outcome::init();
+        legacy_outcome.update_richest(Addr::unchecked("bob"), Uint128::from(2u128));
+        legacy_state(deps.as_mut().storage).save(&legacy_outcome).unwrap();
+        for (acc, networth) in [("alice", 1u128), ("bob", 2), ("carol", 2)] {
+            NETWORTHS.add_suffix(acc.as_bytes()).save(deps.as_mut().storage, &Uint128::from(networth)).unwrap();
+        }
+
+        // an admin is required
+        let seed = Binary::from(b"seed");
+        let msg = MigrateMsg { admin: None, legacy_participants: None, prng_seed: seed.clone() };
+        let res = migrate(deps.as_mut(), mock_env(), msg);
+        assert!(assert_gen_err(res, "An admin must be provided"));
+
+        // the richest must be part of the migrated ranking
+        let mut broken = mock_dependencies();
+        legacy_state(broken.as_mut().storage).save(&legacy_outcome).unwrap();
+        NETWORTHS.add_suffix(b"alice").save(broken.as_mut().storage, &Uint128::from(1u128)).unwrap();
+        let msg = MigrateMsg { admin: Some("admin".to_string()), legacy_participants: None, prng_seed: seed.clone() };
+        let res = migrate(broken.as_mut(), mock_env(), msg);
+        assert!(assert_gen_err(res, "No networth found to migrate for the richest"));
+
+        let msg = MigrateMsg {
+            admin: Some("admin".to_string()),
+            legacy_participants: Some(vec!["carol".to_string(), "alice".to_string()]),
+            prng_seed: seed,
+        };
+        migrate(deps.as_mut(), mock_env(), msg).unwrap();
+
+        // submissions now live in round 0, and the richest is unchanged
+        assert_info_vec(deps.as_ref(), 0, vec![
+            ("alice", false, 1), ("bob", true, 2), ("carol", false, 2)
+        ]);
+        let ranking = RankingStore::load(deps.as_ref().storage, 0).unwrap();
+        assert_eq!(ranking[0], legacy_outcome.richest);
+        assert_eq!(ranking.len(), 3);
+
+        // legacy data is removed
+        assert!(legacy_state(deps.as_mut().storage).may_load().unwrap().is_none());
+        assert!(NETWORTHS.add_suffix(b"alice").is_empty(deps.as_ref().storage));
+
+        // the contract is fully usable
+        let config = CONFIG.load(deps.as_ref().storage).unwrap();
+        assert_eq!(config.admin, Addr::unchecked("admin"));
+        assert_eq!(
+            CONTRACT_VERSION.load(deps.as_ref().storage).unwrap(),
+            ContractVersion { contract: CONTRACT_NAME.to_string(), version: CONTRACT_VERSION_STR.to_string() }
+        );
+        submit_networth_helper(&mut deps, 0, vec![("dan", 3)]);
+        assert_info(deps.as_ref(), 0, "dan", true, 3);
+        assert_eq!(ParticipantCountStore::load(deps.as_ref().storage, 0).unwrap(), 4);
+        let msg = ExecuteMsg::CreateViewingKey { entropy: "entropy".to_string(), padding: None };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("dan", &[]), msg).unwrap();
+        let key = match from_binary(&res.data.unwrap()).unwrap() {
+            HandleAnswer::CreateViewingKey { key } => key,
+            _ => panic!("Unexpected answer"),
+        };
+        assert!(ViewingKey::check(deps.as_ref().storage, "dan", &key).is_ok());
+    }
+
+    #[test]
+    fn test_migrate_version_checks() {
+        let (_, mut deps) = init_helper();
+        submit_networth_helper(&mut deps, 0, vec![("alice", 1)]);
+        let msg = ExecuteMsg::SetViewingKey { key: "key".to_string(), decoys: None, padding: None };
+        execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+        let msg = MigrateMsg { admin: None, legacy_participants: None, prng_seed: Binary::from(b"new seed") };
+
+        // migrating to the same version keeps all state, and viewing keys stay valid with the new seed
+        migrate(deps.as_mut(), mock_env(), msg.clone()).unwrap();
+        assert_info(deps.as_ref(), 0, "alice", true, 1);
+        assert!(ViewingKey::check(deps.as_ref().storage, "alice", "key").is_ok());
+
+        // cannot downgrade
+        let newer = ContractVersion { contract: CONTRACT_NAME.to_string(), version: "99.0.0".to_string() };
+        CONTRACT_VERSION.save(deps.as_mut().storage, &newer).unwrap();
+        let res = migrate(deps.as_mut(), mock_env(), msg.clone());
+        assert!(assert_gen_err(res, "Cannot migrate from newer version"));
+
+        // cannot migrate from another contract
+        let other = ContractVersion { contract: "other-contract".to_string(), version: "0.1.0".to_string() };
+        CONTRACT_VERSION.save(deps.as_mut().storage, &other).unwrap();
+        let res = migrate(deps.as_mut(), mock_env(), msg);
+        assert!(assert_gen_err(res, "Cannot migrate from a different contract"));
+    }
+
+    #[test]
+    fn test_create_viewing_key() {
+        let (_, mut deps) = init_helper();
+        submit_networth_helper(&mut deps, 0, vec![("alice", 1)]);
+
+        let create_key = |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, entropy: &str| {
+            let msg = ExecuteMsg::CreateViewingKey { entropy: entropy.to_string(), padding: None };
+            let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+            match from_binary(&res.data.unwrap()).unwrap() {
+                HandleAnswer::CreateViewingKey { key } => key,
+                answer => panic!("unexpected HandleAnswer type: {answer:?}"),
+            }
+        };
+        let all_info = |key: String| QueryMsg::AllInfo { round_id: 0, addr: Addr::unchecked("alice"), key, padding: None };
+
+        // the returned key can be used for queries
+        let key = create_key(&mut deps, "some entropy");
+        let query_result = query(deps.as_ref(), mock_env(), all_info(key.clone()));
         let query_answer = from_binary::<QueryAnswer>(&query_result.unwrap()).unwrap();
-        assert_eq!(query_answer, QueryAnswer::AllInfo { richest: false, networth: Uint128::one() });
+        assert_eq!(query_answer, QueryAnswer::AllInfo { richest: true, networth: Uint128::one(), snapshot_height: None });
 
-        // AmIRichest
-        let query_result = query(deps.as_ref(), mock_env(), q_msg_richest);
-        assert!(query_result.is_ok());
+        // creating a new key replaces the previous one
+        let new_key = create_key(&mut deps, "some entropy");
+        assert_ne!(new_key, key);
+        let query_result = query(deps.as_ref(), mock_env(), all_info(key));
+        assert_eq!(query_result.unwrap_err(), ContractError::Unauthorized {});
+        assert!(query(deps.as_ref(), mock_env(), all_info(new_key)).is_ok());
+    }
+
+    #[test]
+    fn test_revoke_permit() {
+        use secret_toolkit::permit::{PermitParams, PermitSignature, PubKey};
+
+        let (_, mut deps) = init_helper();
+        // the account derived from the permit's public key
+        let signer = "secret150rtrmj2f8vl9tem8qpfw36ylw5jg9j23x7qy9";
+        submit_networth_helper(&mut deps, 0, vec![(signer, 1)]);
+
+        let permit = Permit {
+            params: PermitParams {
+                allowed_tokens: vec![mock_env().contract.address.into_string()],
+                permit_name: "richie_permit".to_string(),
+                chain_id: "secret-4".to_string(),
+                permissions: vec![RichieRichPermissions::AllInfo],
+            },
+            signature: PermitSignature {
+                pub_key: PubKey {
+                    r#type: "tendermint/PubKeySecp256k1".to_string(),
+                    value: Binary::from_base64("ApicC3bLVjlx/cm+8x7AbDVg8ySdbunl2DxXYlWW4F9v").unwrap(),
+                },
+                signature: Binary::from_base64(
+                    "S6nQX7VJt6yaibv8QYRqeOPV7BRS8jEN1Zf1BxXv+5VgH1zanEkTyIJ8ulmLL95Y20KPHAugwRxmYpeYUQijug=="
+                ).unwrap(),
+            },
+        };
+        let with_permit = |permit: Permit<RichieRichPermissions>| QueryMsg::WithPermit {
+            permit, query: QueryWithPermit::AllInfo { round_id: 0 }, padding: None
+        };
+        let signer_info = QueryAnswer::AllInfo { richest: true, networth: Uint128::one(), snapshot_height: None };
+
+        // a valid permit queries on behalf of its signer
+        let query_result = query(deps.as_ref(), mock_env(), with_permit(permit.clone()));
+        assert_eq!(from_binary::<QueryAnswer>(&query_result.unwrap()).unwrap(), signer_info);
+
+        // permits for another contract, or whose signature does not match, are rejected
+        let mut other_env = mock_env();
+        other_env.contract.address = Addr::unchecked("other_contract");
+        let query_result = query(deps.as_ref(), other_env, with_permit(permit.clone()));
+        assert_eq!(query_result.unwrap_err(), ContractError::Unauthorized {});
+        let mut tampered = permit.clone();
+        tampered.params.permissions.push(RichieRichPermissions::MyRank);
+        let query_result = query(deps.as_ref(), mock_env(), with_permit(tampered));
+        assert_eq!(query_result.unwrap_err(), ContractError::Unauthorized {});
+
+        // revoking a permit only affects permits signed by the sender
+        let msg = ExecuteMsg::RevokePermit { permit_name: "richie_permit".to_string(), padding: None };
+        execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+        let query_result = query(deps.as_ref(), mock_env(), with_permit(permit.clone()));
+        assert_eq!(from_binary::<QueryAnswer>(&query_result.unwrap()).unwrap(), signer_info);
+
+        // as well as only the permit with the given name
+        let msg = ExecuteMsg::RevokePermit { permit_name: "other_permit".to_string(), padding: None };
+        execute(deps.as_mut(), mock_env(), mock_info(signer, &[]), msg).unwrap();
+        let query_result = query(deps.as_ref(), mock_env(), with_permit(permit.clone()));
+        assert_eq!(from_binary::<QueryAnswer>(&query_result.unwrap()).unwrap(), signer_info);
+        let msg = ExecuteMsg::RevokePermit { permit_name: "richie_permit".to_string(), padding: None };
+        execute(deps.as_mut(), mock_env(), mock_info(signer, &[]), msg).unwrap();
+        let query_result = query(deps.as_ref(), mock_env(), with_permit(permit));
+        assert_eq!(query_result.unwrap_err(), ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn test_handle_answers() {
+        let (_, mut deps) = init_helper();
+        let answer = |res: Response| from_binary::<HandleAnswer>(&res.data.unwrap()).unwrap();
+
+        let res = submit_networth_helper(&mut deps, 0, vec![("alice", 1), ("bob", 1), ("carol", 2)]);
+        let answers: Vec<_> = res.into_iter().map(answer).collect();
+        assert_eq!(answers, vec![
+            HandleAnswer::SubmitNetWorth { status: ResponseStatus::Success, became_richest: true },
+            HandleAnswer::SubmitNetWorth { status: ResponseStatus::Success, became_richest: false },
+            HandleAnswer::SubmitNetWorth { status: ResponseStatus::Success, became_richest: true },
+        ]);
+
+        let msg = ExecuteMsg::UpdateNetWorth { round_id: 0, networth: Uint128::zero(), padding: None };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("carol", &[]), msg).unwrap();
+        assert_eq!(answer(res), HandleAnswer::UpdateNetWorth { status: ResponseStatus::Success, richest: false });
+        let msg = ExecuteMsg::UpdateNetWorth { round_id: 0, networth: Uint128::from(3u128), padding: None };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg).unwrap();
+        assert_eq!(answer(res), HandleAnswer::UpdateNetWorth { status: ResponseStatus::Success, richest: true });
+
+        let msg = ExecuteMsg::SetViewingKey { key: "vka".to_string(), decoys: None, padding: None };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+        assert_eq!(answer(res), HandleAnswer::SetViewingKey { status: ResponseStatus::Success });
+
+        let msg = ExecuteMsg::WithdrawSubmission { round_id: 0, padding: None };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+        assert_eq!(answer(res), HandleAnswer::WithdrawSubmission { status: ResponseStatus::Success });
+    }
+
+    #[test]
+    fn test_response_padding() {
+        let (_, mut deps) = init_helper();
+        let res = submit_networth_helper(&mut deps, 0, vec![("alice", 1), ("bob", 2)]);
+        for response in res {
+            assert_eq!(response.data.unwrap().len() % RESPONSE_BLOCK_SIZE, 0);
+        }
+
+        for (acc, key) in [("alice", "vka"), ("bob", "vkb")] {
+            let msg = ExecuteMsg::SetViewingKey { key: key.to_string(), decoys: None, padding: Some("====".to_string()) };
+            execute(deps.as_mut(), mock_env(), mock_info(acc, &[]), msg).unwrap();
+        }
+
+        // answers of different content have the same length
+        let q_msg = |acc: &str, key: &str| QueryMsg::AmIRichest { round_id: 0, addr: Addr::unchecked(acc), key: key.to_string(), padding: None };
+        let not_richest = query(deps.as_ref(), mock_env(), q_msg("alice", "vka")).unwrap();
+        let richest = query(deps.as_ref(), mock_env(), q_msg("bob", "vkb")).unwrap();
+        assert_eq!(not_richest.len(), RESPONSE_BLOCK_SIZE);
+        assert_eq!(richest.len(), RESPONSE_BLOCK_SIZE);
+        assert_eq!(from_binary::<QueryAnswer>(&richest).unwrap(), QueryAnswer::AmIRichest { richest: true });
+
+        // errors are padded like answers, so they cannot be told apart by their length
+        let err = query(deps.as_ref(), mock_env(), q_msg("alice", "wrong")).unwrap_err();
+        assert_eq!(err.to_string().len(), richest.len());
+        let withdraw = ExecuteMsg::WithdrawSubmission { round_id: 0, padding: None };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("carol", &[]), withdraw.clone()).unwrap_err();
+        let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), withdraw).unwrap();
+        assert_eq!(err.to_string().len(), res.data.unwrap().len());
+
+        // padding can be omitted from the json
+        let msg: QueryMsg = from_slice(br#"{"config":{}}"#).unwrap();
+        assert_eq!(msg, QueryMsg::Config { padding: None });
+    }
+
+    #[test]
+    fn test_submission_constant_work() {
+        // "newcomer" submits `networth` in a round where 30 participants already submitted, so the
+        // ranking has several levels, and the storage accesses of the submission are traced
+        let trace_submission = |networth: u128| {
+            let (_, mut deps) = init_helper();
+            let participants: Vec<(String, u128)> = (0..30u128).map(|i| (format!("addr{i:02}"), i * 37 % 30 * 10)).collect();
+            submit_networth_helper(&mut deps, 0, participants.iter().map(|(acc, networth)| (acc.as_str(), *networth)).collect());
+
+            let mut storage = TracingStorage::new(&mut deps.storage);
+            let deps_mut = DepsMut { storage: &mut storage, api: &deps.api, querier: QuerierWrapper::new(&deps.querier) };
+            let msg = ExecuteMsg::SubmitNetWorth { round_id: 0, networth: Uint128::from(networth), decoys: None, padding: None };
+            let res = execute(deps_mut, mock_env(), mock_info("newcomer", &[]), msg).unwrap();
+            let became_richest = match from_binary(&res.data.unwrap()).unwrap() {
+                HandleAnswer::SubmitNetWorth { became_richest, .. } => became_richest,
+                answer => panic!("unexpected HandleAnswer type: {answer:?}"),
+            };
+            (became_richest, storage.trace.into_inner())
+        };
+
+        let (became_richest, richest_trace) = trace_submission(1000);
+        assert!(became_richest);
+        // every position in the ranking, including ties, which take different paths and rotations
+        for networth in (0..=290u128).step_by(5) {
+            let (became_richest, trace) = trace_submission(networth);
+            assert!(!became_richest);
+            for op in ["read", "write", "remove", "range"] {
+                assert_eq!(count_ops(&trace, op), count_ops(&richest_trace, op), "{op} count differs for {networth}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_decoys() {
+        // traces carol's submission in a round where alice and bob already submitted
+        let trace_submission = |decoys: Option<Vec<Addr>>| {
+            let (_, mut deps) = init_helper();
+            submit_networth_helper(&mut deps, 0, vec![("alice", 1), ("bob", 3)]);
+
+            let mut storage = TracingStorage::new(&mut deps.storage);
+            let deps_mut = DepsMut { storage: &mut storage, api: &deps.api, querier: QuerierWrapper::new(&deps.querier) };
+            let msg = ExecuteMsg::SubmitNetWorth { round_id: 0, networth: Uint128::from(2u128), decoys, padding: None };
+            execute(deps_mut, mock_env(), mock_info("carol", &[]), msg).unwrap();
+            let trace = storage.trace.into_inner();
+            (deps, trace)
+        };
+
+        let (_, trace) = trace_submission(None);
+        let decoys = ["alice", "bob", "dave"].iter().map(|acc| Addr::unchecked(*acc)).collect();
+        let (mut deps, decoy_trace) = trace_submission(Some(decoys));
+
+        // the submission of every decoy is read and written like the sender's, even if it has none
+        assert_eq!(count_ops(&decoy_trace, "read"), count_ops(&trace, "read") + 3);
+        assert_eq!(count_ops(&decoy_trace, "write"), count_ops(&trace, "write") + 3);
+        // every key of the sender is accessed the same way for each decoy, with values of the same length
+        let sender_ops = account_ops(&decoy_trace, "carol");
+        assert_eq!(sender_ops.iter().map(|(op, ..)| *op).collect::<Vec<_>>(), vec!["read", "write"]);
+        assert!(sender_ops[0].1.starts_with(PREFIX_NETWORTHS));
+        for decoy in ["alice", "bob", "dave"] {
+            assert_eq!(account_ops(&decoy_trace, decoy), sender_ops);
+        }
+        assert_info_vec(deps.as_ref(), 0, vec![("alice", false, 1), ("bob", true, 3), ("carol", false, 2)]);
+        assert_eq!(NetWorthStore::may_load(deps.as_ref().storage, 0, &Addr::unchecked("dave")).unwrap(), None);
+        let ranking = RankingStore::load(deps.as_ref().storage, 0).unwrap();
+        assert_eq!(ranking.len(), 3);
+
+        // decoys keep their viewing keys
+        let msg = ExecuteMsg::SetViewingKey { key: "vka".to_string(), decoys: None, padding: None };
+        execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+        let decoys = vec![Addr::unchecked("alice"), Addr::unchecked("dave")];
+        let msg = ExecuteMsg::SetViewingKey { key: "vkc".to_string(), decoys: Some(decoys), padding: None };
+        let mut storage = TracingStorage::new(&mut deps.storage);
+        let deps_mut = DepsMut { storage: &mut storage, api: &deps.api, querier: QuerierWrapper::new(&deps.querier) };
+        execute(deps_mut, mock_env(), mock_info("carol", &[]), msg).unwrap();
+        let key_trace = storage.trace.into_inner();
+        let sender_ops = account_ops(&key_trace, "carol");
+        assert_eq!(sender_ops.iter().map(|(op, ..)| *op).collect::<Vec<_>>(), vec!["read", "write"]);
+        assert_eq!(account_ops(&key_trace, "alice"), sender_ops);
+        assert_eq!(account_ops(&key_trace, "dave"), sender_ops);
+        assert!(ViewingKey::check(deps.as_ref().storage, "alice", "vka").is_ok());
+        assert!(ViewingKey::check(deps.as_ref().storage, "carol", "vkc").is_ok());
+        assert!(ViewingKey::check(deps.as_ref().storage, "dave", "vkc").is_err());
+
+        // deposits of the escrow token are written for decoys too, without changing theirs
+        let token = Snip20Token { address: Addr::unchecked("token"), code_hash: "token_hash".to_string() };
+        let round_id = create_round_helper(&mut deps, "creator", RoundParams {
+            end_height: Some(mock_env().block.height + 10), escrow_token: Some(token), ..Default::default()
+        });
+        let deposit = |from: &str, amount: u128, decoys| ExecuteMsg::Receive {
+            sender: Addr::unchecked(from),
+            from: Addr::unchecked(from),
+            amount: Uint128::from(amount),
+            memo: None,
+            msg: Some(to_binary(&ReceiveMsg::Deposit { round_id, decoys }).unwrap()),
+            padding: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("token", &[]), deposit("alice", 100, None)).unwrap();
+        let decoys = vec![Addr::unchecked("alice"), Addr::unchecked("dave")];
+        let mut storage = TracingStorage::new(&mut deps.storage);
+        let deps_mut = DepsMut { storage: &mut storage, api: &deps.api, querier: QuerierWrapper::new(&deps.querier) };
+        execute(deps_mut, mock_env(), mock_info("token", &[]), deposit("carol", 50, Some(decoys))).unwrap();
+        let deposit_trace = storage.trace.into_inner();
+        let sender_ops = account_ops(&deposit_trace, "carol");
+        assert!(sender_ops.iter().any(|(_, key, _)| key.starts_with(PREFIX_DEPOSITS)));
+        assert_eq!(account_ops(&deposit_trace, "alice"), sender_ops);
+        assert_eq!(account_ops(&deposit_trace, "dave"), sender_ops);
+        let storage = deps.as_mut().storage;
+        assert_eq!(DepositStore::take(storage, round_id, &Addr::unchecked("alice")).unwrap(), Some(Uint128::from(100u128)));
+        assert_eq!(DepositStore::take(storage, round_id, &Addr::unchecked("carol")).unwrap(), Some(Uint128::from(50u128)));
+        assert_eq!(DepositStore::take(storage, round_id, &Addr::unchecked("dave")).unwrap(), None);
+
+        // a decoy that is the sender is ignored
+        let msg = ExecuteMsg::SubmitNetWorth {
+            round_id: 0, networth: Uint128::one(), decoys: Some(vec![Addr::unchecked("erin")]), padding: None
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("erin", &[]), msg).unwrap();
+        assert_info(deps.as_ref(), 0, "erin", false, 1);
+    }
+
+    #[test]
+    fn test_batch_query() {
+        let (_, mut deps) = init_helper();
+        submit_networth_helper(&mut deps, 0, vec![("alice", 1), ("bob", 2)]);
+        let msg = ExecuteMsg::SetViewingKey { key: "vka".to_string(), decoys: None, padding: None };
+        execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+
+        let batch = |key: &str, queries: Vec<QueryWithPermit>| QueryMsg::Batch {
+            addr: Addr::unchecked("alice"), key: key.to_string(), queries, padding: None
+        };
+        let queries = vec![
+            QueryWithPermit::AllInfo { round_id: 0 },
+            QueryWithPermit::AmIRichest { round_id: 0 },
+            QueryWithPermit::MyRank { round_id: 0 },
+        ];
+
+        let query_result = query(deps.as_ref(), mock_env(), batch("vka", queries.clone()));
         let query_answer = from_binary::<QueryAnswer>(&query_result.unwrap()).unwrap();
-        assert_eq!(query_answer, QueryAnswer::AmIRichest { richest: false });
+        assert_eq!(query_answer, QueryAnswer::Batch { answers: vec![
+            QueryAnswer::AllInfo { richest: false, networth: Uint128::one(), snapshot_height: None },
+            QueryAnswer::AmIRichest { richest: false },
+            QueryAnswer::MyRank { rank: 2, participants: 2 },
+        ]});
 
-        // cannot view result with wrong vk ----------------------
-        // AllInfo
-        let q_msg_wrong_vk_all = QueryMsg::AllInfo { addr: Addr::unchecked("alice"), key: "vk_wrong".to_string() };
-        let query_result = query(deps.as_ref(), mock_env(), q_msg_wrong_vk_all);
-        assert_gen_err(query_result, "Wrong viewing key for this address or viewing key not set");
+        let query_result = query(deps.as_ref(), mock_env(), batch("vk_wrong", queries.clone()));
+        assert_eq!(query_result.unwrap_err(), ContractError::Unauthorized {});
 
-        // AmIRichest
-        let q_msg_wrong_vk_richest = QueryMsg::AmIRichest { addr: Addr::unchecked("alice"), key: "vk_wrong".to_string() };
-        let query_result = query(deps.as_ref(), mock_env(), q_msg_wrong_vk_richest);
-        assert_gen_err(query_result, "Wrong viewing key for this address or viewing key not set");
+        let nested = vec![QueryWithPermit::Batch { queries: queries.clone() }];
+        let query_result = query(deps.as_ref(), mock_env(), batch("vka", nested));
+        assert!(assert_gen_err(query_result, "Batch queries cannot be nested"));
 
-        // cannot view result with "wrong address" ----------------------
-        // AllInfo
-        let q_msg_wrong_addr_all = QueryMsg::AllInfo { addr: Addr::unchecked("bob"), key: "vka".to_string() };
-        let query_result = query(deps.as_ref(), mock_env(), q_msg_wrong_addr_all);
-        assert_gen_err(query_result, "Wrong viewing key for this address or viewing key not set");
-        
-        // AmIRichest
-        let q_msg_wrong_addr_richest = QueryMsg::AmIRichest { addr: Addr::unchecked("bob"), key: "vka".to_string() };
-        let query_result = query(deps.as_ref(), mock_env(), q_msg_wrong_addr_richest);
-        assert_gen_err(query_result, "Wrong viewing key for this address or viewing key not set");
-        
+        // a permit needs the permissions of every batched query
+        let permit_batch = QueryWithPermit::Batch { queries };
+        assert_eq!(permit_batch.required_permissions(), vec![
+            RichieRichPermissions::AllInfo, RichieRichPermissions::AmIRichest, RichieRichPermissions::MyRank,
+        ]);
+    }
+
+    #[test]
+    fn test_contest_info() {
+        let (_, mut deps) = init_helper();
+        let contest_info = |deps: Deps| {
+            let msg = QueryMsg::ContestInfo { round_id: 0, padding: None };
+            from_binary::<QueryAnswer>(&query(deps, mock_env(), msg).unwrap()).unwrap()
+        };
+        let height = mock_env().block.height;
+        assert_eq!(contest_info(deps.as_ref()), QueryAnswer::ContestInfo {
+            participants: 0, status: RoundStatus::Open, created_height: height,
+            entry_fee: None, prize_pool: Uint128::zero(), verified_denoms: None, include_delegations: false, escrow_token: None, reveal_deadline: None,
+        });
+
+        submit_networth_helper(&mut deps, 0, vec![("alice", 1), ("bob", 2), ("carol", 3)]);
+        let msg = ExecuteMsg::UpdateNetWorth { round_id: 0, networth: Uint128::from(4u128), padding: None };
+        execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+        let msg = ExecuteMsg::WithdrawSubmission { round_id: 0, padding: None };
+        execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg).unwrap();
+        assert_eq!(contest_info(deps.as_ref()), QueryAnswer::ContestInfo {
+            participants: 2, status: RoundStatus::Open, created_height: height,
+            entry_fee: None, prize_pool: Uint128::zero(), verified_denoms: None, include_delegations: false, escrow_token: None, reveal_deadline: None,
+        });
+
+        let msg = QueryMsg::ContestInfo { round_id: 1, padding: None };
+        assert!(query(deps.as_ref(), mock_env(), msg).is_err());
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let errors = vec![
+            ContractError::Std(StdError::generic_err("error")),
+            ContractError::Unauthorized {},
+            ContractError::NoPermission {
+                required: vec![RichieRichPermissions::MyRank],
+                granted: vec![RichieRichPermissions::AllInfo],
+            },
+            ContractError::ContractStopped {},
+            ContractError::AlreadySubmittedNetworth { networth: Uint128::one() },
+            ContractError::NotSubmitted {},
+            ContractError::RoundNotFound { round_id: 1 },
+            ContractError::RoundClosed { round_id: 1 },
+            ContractError::RoundStillOpen { round_id: 1 },
+            ContractError::RoundAlreadyFinalized { round_id: 1 },
+            ContractError::RoundNotFinalized { round_id: 1 },
+            ContractError::RoundCancelled { round_id: 1 },
+            ContractError::InvalidEntryFee { expected: coin(1, "uscrt") },
+            ContractError::UnexpectedFunds {},
+            ContractError::NoPrize { round_id: 1 },
+            ContractError::VerificationRequired { round_id: 1 },
+            ContractError::NotVerifiedRound { round_id: 1 },
+            ContractError::EscrowRequired { round_id: 1 },
+            ContractError::InvalidToken { round_id: 1 },
+            ContractError::EscrowLocked { round_id: 1 },
+            ContractError::NothingToWithdraw { round_id: 1 },
+            ContractError::CommitRequired { round_id: 1 },
+            ContractError::NotCommitRevealRound { round_id: 1 },
+            ContractError::AlreadyCommitted {},
+            ContractError::NotCommitted {},
+            ContractError::NotRevealPeriod { round_id: 1 },
+            ContractError::InvalidReveal {},
+        ];
+        for err in errors {
+            assert!(err.to_string().starts_with(&format!("[{}] ", err.code())), "{err}");
+        }
+
+        // clients receive the code of query errors
+        let (_, deps) = init_helper();
+        let msg = QueryMsg::MyRank { round_id: 0, addr: Addr::unchecked("alice"), key: "vka".to_string(), padding: None };
+        let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+        assert_eq!(err.code(), "unauthorized");
+        assert!(err.to_string().starts_with("[unauthorized] "));
+    }
+
+    #[test]
+    fn test_entry_fee() {
+        let (_, mut deps) = init_helper();
+        let env = mock_env();
+        let msg = create_round_msg(RoundParams { entry_fee: Some(coin(0, "uscrt")), ..Default::default() });
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg);
+        assert!(assert_gen_err(res, "cannot be zero"));
+        let msg = create_round_msg(RoundParams {
+            end_height: Some(env.block.height + 10), entry_fee: Some(coin(100, "uscrt")), ..Default::default()
+        });
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &coins(1, "uscrt")), msg.clone());
+        assert_eq!(res.unwrap_err(), ContractError::UnexpectedFunds {});
+        execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+
+        // the exact fee must be sent
+        let submit = ExecuteMsg::SubmitNetWorth { round_id: 1, networth: Uint128::from(1u128), decoys: None, padding: None };
+        for funds in [vec![], coins(99, "uscrt"), coins(100, "uatom"), vec![coin(100, "uscrt"), coin(1, "uatom")]] {
+            let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &funds), submit.clone());
+            assert_eq!(res.unwrap_err(), ContractError::InvalidEntryFee { expected: coin(100, "uscrt") });
+        }
+        // rounds without a fee do not accept funds
+        let msg = ExecuteMsg::SubmitNetWorth { round_id: 0, networth: Uint128::from(1u128), decoys: None, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &coins(100, "uscrt")), msg);
+        assert_eq!(res.unwrap_err(), ContractError::UnexpectedFunds {});
+
+        execute(deps.as_mut(), env.clone(), mock_info("alice", &coins(100, "uscrt")), submit.clone()).unwrap();
+        let submit = ExecuteMsg::SubmitNetWorth { round_id: 1, networth: Uint128::from(2u128), decoys: None, padding: None };
+        execute(deps.as_mut(), env.clone(), mock_info("bob", &coins(100, "uscrt")), submit).unwrap();
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::ContestInfo { round_id: 1, padding: None }).unwrap();
+        assert_eq!(from_binary::<QueryAnswer>(&res).unwrap(), QueryAnswer::ContestInfo {
+            participants: 2, status: RoundStatus::Open, created_height: env.block.height,
+            entry_fee: Some(coin(100, "uscrt")), prize_pool: Uint128::from(200u128), verified_denoms: None, include_delegations: false, escrow_token: None, reveal_deadline: None,
+        });
+
+        // leaving an open round forfeits the fee to the prize pool
+        let submit = ExecuteMsg::SubmitNetWorth { round_id: 1, networth: Uint128::from(3u128), decoys: None, padding: None };
+        execute(deps.as_mut(), env.clone(), mock_info("carol", &coins(100, "uscrt")), submit).unwrap();
+        let msg = ExecuteMsg::WithdrawSubmission { round_id: 1, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("carol", &[]), msg).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(PrizePoolStore::load(deps.as_ref().storage, 1).unwrap(), Uint128::from(300u128));
+
+        // the prize can only be claimed once the round is finalized
+        let claim = ExecuteMsg::ClaimPrize { round_id: 1, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), claim.clone());
+        assert_eq!(res.unwrap_err(), ContractError::RoundNotFinalized { round_id: 1 });
+
+        let mut env_later = mock_env();
+        env_later.block.height += 10;
+        // nor can its fees be taken back once the round has ended
+        let msg = ExecuteMsg::WithdrawSubmission { round_id: 1, padding: None };
+        let res = execute(deps.as_mut(), env_later.clone(), mock_info("alice", &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::RoundClosed { round_id: 1 });
+        let msg = ExecuteMsg::FinalizeRound { round_id: 1, padding: None };
+        execute(deps.as_mut(), env_later.clone(), mock_info("anyone", &[]), msg).unwrap();
+        let res = execute(deps.as_mut(), env_later.clone(), mock_info("anyone", &[]), claim.clone()).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send { to_address: "bob".to_string(), amount: coins(300, "uscrt") })
+        );
+        assert_eq!(PrizePoolStore::load(deps.as_ref().storage, 1).unwrap(), Uint128::zero());
+        let res = execute(deps.as_mut(), env_later, mock_info("anyone", &[]), claim);
+        assert_eq!(res.unwrap_err(), ContractError::NoPrize { round_id: 1 });
+    }
+
+    #[test]
+    fn test_cancel_round() {
+        let (_, mut deps) = init_helper();
+        let env = mock_env();
+        create_round_helper(&mut deps, "alice", RoundParams {
+            entry_fee: Some(coin(100, "uscrt")), ..Default::default()
+        });
+        for sender in ["bob", "carol"] {
+            let msg = ExecuteMsg::SubmitNetWorth { round_id: 1, networth: Uint128::from(1u128), decoys: None, padding: None };
+            execute(deps.as_mut(), env.clone(), mock_info(sender, &coins(100, "uscrt")), msg).unwrap();
+        }
+
+        // only the round's creator or the admin can cancel it
+        let cancel = ExecuteMsg::CancelRound { round_id: 1, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), cancel.clone());
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), cancel.clone()).unwrap();
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), cancel);
+        assert_eq!(res.unwrap_err(), ContractError::RoundCancelled { round_id: 1 });
+
+        let msg = ExecuteMsg::SubmitNetWorth { round_id: 1, networth: Uint128::from(1u128), decoys: None, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("dave", &coins(100, "uscrt")), msg);
+        assert_eq!(res.unwrap_err(), ContractError::RoundClosed { round_id: 1 });
+        let msg = ExecuteMsg::FinalizeRound { round_id: 1, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::RoundCancelled { round_id: 1 });
+
+        // participants get their entry fees back by withdrawing
+        let msg = ExecuteMsg::WithdrawSubmission { round_id: 1, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send { to_address: "bob".to_string(), amount: coins(100, "uscrt") })
+        );
+        assert_eq!(PrizePoolStore::load(deps.as_ref().storage, 1).unwrap(), Uint128::from(100u128));
+
+        // finalized rounds cannot be cancelled
+        create_round_helper(&mut deps, "alice", RoundParams {
+            end_height: Some(env.block.height + 1), ..Default::default()
+        });
+        let mut env_later = mock_env();
+        env_later.block.height += 1;
+        let msg = ExecuteMsg::FinalizeRound { round_id: 2, padding: None };
+        execute(deps.as_mut(), env_later.clone(), mock_info("anyone", &[]), msg).unwrap();
+        let msg = ExecuteMsg::CancelRound { round_id: 2, padding: None };
+        let res = execute(deps.as_mut(), env_later, mock_info("alice", &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::RoundAlreadyFinalized { round_id: 2 });
+    }
+
+    #[test]
+    fn test_verified_networth() {
+        let (_, mut deps) = init_helper();
+        let env = mock_env();
+        deps.querier.update_balance("alice", vec![coin(100, "uscrt"), coin(20, "uatom"), coin(5, "ujuno")]);
+        deps.querier.update_balance("bob", coins(150, "uscrt"));
+
+        let msg = create_round_msg(RoundParams { verified_denoms: Some(vec![]), ..Default::default() });
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg);
+        assert!(assert_gen_err(res, "At least one denom"));
+        let denoms = vec!["uscrt".to_string(), "uatom".to_string()];
+        create_round_helper(&mut deps, "creator", RoundParams {
+            verified_denoms: Some(denoms.clone()), ..Default::default()
+        });
+
+        // self-reported networths are rejected in verified rounds, and vice versa
+        let msg = ExecuteMsg::SubmitNetWorth { round_id: 1, networth: Uint128::from(1000u128), decoys: None, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::VerificationRequired { round_id: 1 });
+        let msg = ExecuteMsg::SubmitVerifiedNetWorth { round_id: 0, decoys: None, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::NotVerifiedRound { round_id: 0 });
+
+        // only the verified denoms count
+        let submit = ExecuteMsg::SubmitVerifiedNetWorth { round_id: 1, decoys: None, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), submit.clone()).unwrap();
+        assert_eq!(
+            from_binary::<HandleAnswer>(&res.data.unwrap()).unwrap(),
+            HandleAnswer::SubmitVerifiedNetWorth { status: ResponseStatus::Success, networth: Uint128::from(120u128), became_richest: true }
+        );
+        let res = execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), submit.clone()).unwrap();
+        assert_eq!(
+            from_binary::<HandleAnswer>(&res.data.unwrap()).unwrap(),
+            HandleAnswer::SubmitVerifiedNetWorth { status: ResponseStatus::Success, networth: Uint128::from(150u128), became_richest: true }
+        );
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), submit);
+        assert_eq!(res.unwrap_err(), ContractError::AlreadySubmittedNetworth { networth: Uint128::from(120u128) });
+        let msg = ExecuteMsg::UpdateNetWorth { round_id: 1, networth: Uint128::from(1000u128), padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::VerificationRequired { round_id: 1 });
+
+        // the snapshot height is kept along with the networth
+        assert_eq!(
+            query_all_info(deps.as_ref(), 1, Addr::unchecked("alice")).unwrap(),
+            QueryAnswer::AllInfo { richest: false, networth: Uint128::from(120u128), snapshot_height: Some(env.block.height) }
+        );
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::ContestInfo { round_id: 1, padding: None }).unwrap();
+        assert_eq!(from_binary::<QueryAnswer>(&res).unwrap(), QueryAnswer::ContestInfo {
+            participants: 2, status: RoundStatus::Open, created_height: env.block.height,
+            entry_fee: None, prize_pool: Uint128::zero(), verified_denoms: Some(denoms), include_delegations: false, escrow_token: None, reveal_deadline: None,
+        });
+
+        let msg = ExecuteMsg::WithdrawSubmission { round_id: 1, padding: None };
+        execute(deps.as_mut(), env, mock_info("alice", &[]), msg).unwrap();
+        assert_eq!(SnapshotHeightStore::may_load(deps.as_ref().storage, 1, &Addr::unchecked("alice")).unwrap(), None);
+    }
+
+    #[test]
+    fn test_verified_networth_with_delegations() {
+        let (_, mut deps) = init_helper();
+        let env = mock_env();
+        let delegation = |delegator: &str, amount: u128| FullDelegation {
+            delegator: Addr::unchecked(delegator),
+            validator: "validator".to_string(),
+            amount: coin(amount, "uscrt"),
+            can_redelegate: coin(amount, "uscrt"),
+            accumulated_rewards: vec![],
+        };
+        deps.querier.update_staking("uscrt", &[], &[delegation("alice", 50), delegation("alice", 30)]);
+        deps.querier.update_balance("alice", coins(100, "uscrt"));
+        deps.querier.update_balance("bob", coins(150, "uscrt"));
+
+        let msg = create_round_msg(RoundParams { include_delegations: Some(true), ..Default::default() });
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg);
+        assert!(assert_gen_err(res, "only be included with verified denoms"));
+        for include_delegations in [Some(true), None] {
+            create_round_helper(&mut deps, "creator", RoundParams {
+                verified_denoms: Some(vec!["uscrt".to_string()]), include_delegations, ..Default::default()
+            });
+        }
+
+        // delegated tokens count in round 1 only, and bob has no delegations
+        for round_id in 1..3 {
+            for sender in ["alice", "bob"] {
+                let msg = ExecuteMsg::SubmitVerifiedNetWorth { round_id, decoys: None, padding: None };
+                execute(deps.as_mut(), env.clone(), mock_info(sender, &[]), msg).unwrap();
+            }
+        }
+        assert_info_vec(deps.as_ref(), 1, vec![("alice", true, 180), ("bob", false, 150)]);
+        assert_info_vec(deps.as_ref(), 2, vec![("alice", false, 100), ("bob", true, 150)]);
+
+        let res = query(deps.as_ref(), env, QueryMsg::ContestInfo { round_id: 1, padding: None }).unwrap();
+        match from_binary::<QueryAnswer>(&res).unwrap() {
+            QueryAnswer::ContestInfo { include_delegations, .. } => assert!(include_delegations),
+            res => panic!("unexpected QueryAnswer type: {res:?}"),
+        }
+    }
+
+    #[test]
+    fn test_escrow() {
+        let (_, mut deps) = init_helper();
+        let env = mock_env();
+        let token = Snip20Token { address: Addr::unchecked("token"), code_hash: "token_hash".to_string() };
+        let create = |entry_fee| create_round_msg(RoundParams {
+            end_height: Some(env.block.height + 10), entry_fee, escrow_token: Some(token.clone()), ..Default::default()
+        });
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), create(Some(coin(1, "uscrt"))));
+        assert!(assert_gen_err(res, "cannot have an entry fee"));
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), create(None)).unwrap();
+        let register = snip20::register_receive_msg(
+            env.contract.code_hash.clone(), None, RESPONSE_BLOCK_SIZE, "token_hash".to_string(), "token".to_string(),
+        ).unwrap();
+        assert_eq!(res.messages[0].msg, register);
+
+        let msg = ExecuteMsg::SubmitNetWorth { round_id: 1, networth: Uint128::from(1000u128), decoys: None, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::EscrowRequired { round_id: 1 });
+
+        // deposits of the escrow token become networths
+        let deposit = |round_id, from: &str, amount: u128| ExecuteMsg::Receive {
+            sender: Addr::unchecked(from),
+            from: Addr::unchecked(from),
+            amount: Uint128::from(amount),
+            memo: None,
+            msg: Some(to_binary(&ReceiveMsg::Deposit { round_id, decoys: None }).unwrap()),
+            padding: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("other_token", &[]), deposit(1, "alice", 100));
+        assert_eq!(res.unwrap_err(), ContractError::InvalidToken { round_id: 1 });
+        let res = execute(deps.as_mut(), env.clone(), mock_info("token", &[]), deposit(0, "alice", 100));
+        assert_eq!(res.unwrap_err(), ContractError::InvalidToken { round_id: 0 });
+        execute(deps.as_mut(), env.clone(), mock_info("token", &[]), deposit(1, "alice", 100)).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("token", &[]), deposit(1, "bob", 50)).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("token", &[]), deposit(1, "carol", 200)).unwrap();
+        assert_info_vec(deps.as_ref(), 1, vec![("alice", false, 100), ("bob", false, 50), ("carol", true, 200)]);
+
+        // deposits are locked while the round is open, unless the submission is withdrawn
+        let withdraw = ExecuteMsg::Withdraw { round_id: 1, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), withdraw.clone());
+        assert_eq!(res.unwrap_err(), ContractError::EscrowLocked { round_id: 1 });
+        let msg = ExecuteMsg::WithdrawSubmission { round_id: 1, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("carol", &[]), msg).unwrap();
+        let transfer = |recipient: &str, amount: u128| snip20::transfer_msg(
+            recipient.to_string(), Uint128::from(amount), None, None, RESPONSE_BLOCK_SIZE,
+            "token_hash".to_string(), "token".to_string(),
+        ).unwrap();
+        assert_eq!(res.messages[0].msg, transfer("carol", 200));
+
+        let mut env_later = mock_env();
+        env_later.block.height += 10;
+        let res = execute(deps.as_mut(), env_later.clone(), mock_info("alice", &[]), withdraw.clone()).unwrap();
+        assert_eq!(res.messages[0].msg, transfer("alice", 100));
+        assert_eq!(
+            from_binary::<HandleAnswer>(&res.data.unwrap()).unwrap(),
+            HandleAnswer::Withdraw { status: ResponseStatus::Success, amount: Uint128::from(100u128) }
+        );
+        let res = execute(deps.as_mut(), env_later.clone(), mock_info("alice", &[]), withdraw);
+        assert_eq!(res.unwrap_err(), ContractError::NothingToWithdraw { round_id: 1 });
+        // withdrawing after the round ended does not change its outcome
+        assert_info(deps.as_ref(), 1, "alice", true, 100);
+    }
+
+    #[test]
+    fn test_commit_reveal() {
+        let (_, mut deps) = init_helper();
+        let env = mock_env();
+        let end = env.block.height + 10;
+        let create = |end_height, reveal_deadline| create_round_msg(RoundParams {
+            end_height, reveal_deadline, ..Default::default()
+        });
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), create(None, Some(end)));
+        assert!(assert_gen_err(res, "need an end height"));
+        let msg = create_round_msg(RoundParams {
+            end_time: Some(env.block.time.seconds() + 60), reveal_deadline: Some(end), ..Default::default()
+        });
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg);
+        assert!(assert_gen_err(res, "need an end height"));
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), create(Some(end), Some(end)));
+        assert!(assert_gen_err(res, "must be after the end"));
+        execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), create(Some(end), Some(end + 10))).unwrap();
+
+        let msg = ExecuteMsg::SubmitNetWorth { round_id: 1, networth: Uint128::from(1u128), decoys: None, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::CommitRequired { round_id: 1 });
+
+        let commit = |round_id, networth: u128, salt: &str| ExecuteMsg::CommitNetWorth {
+            round_id, commitment: commitment_of(Uint128::from(networth), salt), padding: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), commit(0, 100, "salt"));
+        assert_eq!(res.unwrap_err(), ContractError::NotCommitRevealRound { round_id: 0 });
+        for (sender, networth) in [("alice", 100), ("bob", 200), ("carol", 300), ("erin", 400)] {
+            execute(deps.as_mut(), env.clone(), mock_info(sender, &[]), commit(1, networth, sender)).unwrap();
+        }
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), commit(1, 100, "alice"));
+        assert_eq!(res.unwrap_err(), ContractError::AlreadyCommitted {});
+
+        // an unrevealed commitment can be withdrawn, leaving nothing behind
+        let withdraw = ExecuteMsg::WithdrawSubmission { round_id: 1, padding: None };
+        execute(deps.as_mut(), env.clone(), mock_info("erin", &[]), withdraw.clone()).unwrap();
+        assert_eq!(CommitmentStore::may_load(deps.as_ref().storage, 1, &Addr::unchecked("erin")).unwrap(), None);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("erin", &[]), withdraw.clone());
+        assert_eq!(res.unwrap_err(), ContractError::NotSubmitted {});
+
+        // commitments are revealed after the round ends, and before the reveal deadline
+        let reveal = |networth: u128, salt: &str| ExecuteMsg::RevealNetWorth {
+            round_id: 1, networth: Uint128::from(networth), salt: salt.to_string(), padding: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), reveal(100, "alice"));
+        assert_eq!(res.unwrap_err(), ContractError::NotRevealPeriod { round_id: 1 });
+        let mut env_reveal = mock_env();
+        env_reveal.block.height = end;
+        let res = execute(deps.as_mut(), env_reveal.clone(), mock_info("alice", &[]), reveal(101, "alice"));
+        assert_eq!(res.unwrap_err(), ContractError::InvalidReveal {});
+        let res = execute(deps.as_mut(), env_reveal.clone(), mock_info("dave", &[]), reveal(100, "dave"));
+        assert_eq!(res.unwrap_err(), ContractError::NotCommitted {});
+        execute(deps.as_mut(), env_reveal.clone(), mock_info("alice", &[]), reveal(100, "alice")).unwrap();
+        let res = execute(deps.as_mut(), env_reveal.clone(), mock_info("bob", &[]), reveal(200, "bob")).unwrap();
+        assert_eq!(
+            from_binary::<HandleAnswer>(&res.data.unwrap()).unwrap(),
+            HandleAnswer::RevealNetWorth { status: ResponseStatus::Success, became_richest: true }
+        );
+        let res = execute(deps.as_mut(), env_reveal.clone(), mock_info("alice", &[]), reveal(100, "alice"));
+        assert_eq!(res.unwrap_err(), ContractError::NotCommitted {});
+
+        // the round is finalized after the reveal deadline, without carol's unrevealed commitment
+        let finalize = ExecuteMsg::FinalizeRound { round_id: 1, padding: None };
+        let res = execute(deps.as_mut(), env_reveal, mock_info("anyone", &[]), finalize.clone());
+        assert_eq!(res.unwrap_err(), ContractError::RoundStillOpen { round_id: 1 });
+        let mut env_later = mock_env();
+        env_later.block.height = end + 10;
+        let res = execute(deps.as_mut(), env_later.clone(), mock_info("carol", &[]), reveal(300, "carol"));
+        assert_eq!(res.unwrap_err(), ContractError::NotRevealPeriod { round_id: 1 });
+        execute(deps.as_mut(), env_later.clone(), mock_info("anyone", &[]), finalize).unwrap();
+        let res = query(deps.as_ref(), env_later.clone(), QueryMsg::Winner { round_id: 1, padding: None }).unwrap();
+        assert_eq!(
+            from_binary::<QueryAnswer>(&res).unwrap(),
+            QueryAnswer::Winner { winner: Some(Addr::unchecked("bob")), networth: None }
+        );
+        assert_eq!(ParticipantCountStore::load(deps.as_ref().storage, 1).unwrap(), 2);
+
+        // even once the round is finalized
+        execute(deps.as_mut(), env_later, mock_info("carol", &[]), withdraw).unwrap();
+        assert_eq!(CommitmentStore::may_load(deps.as_ref().storage, 1, &Addr::unchecked("carol")).unwrap(), None);
+    }
+
+    #[test]
+    fn test_close_round() {
+        let (_, mut deps) = init_helper();
+        let env = mock_env();
+        submit_networth_helper(&mut deps, 0, vec![("alice", 1), ("bob", 2)]);
+
+        // round 0 has no deadline, so it stays open until closed
+        let finalize = ExecuteMsg::FinalizeRound { round_id: 0, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), finalize.clone());
+        assert_eq!(res.unwrap_err(), ContractError::RoundStillOpen { round_id: 0 });
+        let close = ExecuteMsg::CloseRound { round_id: 0, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), close.clone());
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), close.clone()).unwrap();
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), close);
+        assert!(assert_gen_err(res, "without a deadline"));
+
+        let msg = ExecuteMsg::SubmitNetWorth { round_id: 0, networth: Uint128::from(3u128), decoys: None, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("carol", &[]), msg);
+        assert_eq!(res.unwrap_err(), ContractError::RoundClosed { round_id: 0 });
+        execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), finalize).unwrap();
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Winner { round_id: 0, padding: None }).unwrap();
+        assert_eq!(
+            from_binary::<QueryAnswer>(&res).unwrap(),
+            QueryAnswer::Winner { winner: Some(Addr::unchecked("bob")), networth: None }
+        );
+
+        // rounds with a deadline close on their own
+        create_round_helper(&mut deps, "alice", RoundParams {
+            end_height: Some(env.block.height + 1), ..Default::default()
+        });
+        let msg = ExecuteMsg::CloseRound { round_id: 1, padding: None };
+        let res = execute(deps.as_mut(), env, mock_info("alice", &[]), msg);
+        assert!(assert_gen_err(res, "without a deadline"));
+    }
+
+    #[test]
+    fn test_ranking_tree() {
+        let (_, mut deps) = init_helper();
+        let storage = deps.as_mut().storage;
+        // networths with many ties, in a scrambled order
+        let networth = |i: u128| Uint128::from(i * 7919 % 50);
+        let mut expected: Vec<Millionaire> = vec![];
+        for i in 0..200u128 {
+            let millionaire = Millionaire { addr: Addr::unchecked(format!("addr{i}")), networth: networth(i) };
+            // ids are assigned in insertion order
+            assert_eq!(RankingStore::insert(storage, 0, millionaire.clone()).unwrap(), i as u64);
+            let pos = expected.partition_point(|m| m.networth >= millionaire.networth);
+            expected.insert(pos, millionaire);
+        }
+        for i in (0..200u128).step_by(3) {
+            let addr = Addr::unchecked(format!("addr{i}"));
+            RankingStore::remove(storage, 0, i as u64).unwrap();
+            expected.retain(|m| m.addr != addr);
+        }
+        assert_eq!(RankingStore::load(storage, 0).unwrap(), expected);
+        assert_eq!(RankingStore::len(storage, 0).unwrap(), expected.len() as u32);
+        assert_eq!(RankingStore::first(storage, 0).unwrap(), expected.first().cloned());
+        for networth in 0..51u128 {
+            let rank = expected.partition_point(|m| m.networth > Uint128::from(networth)) as u32 + 1;
+            assert_eq!(RankingStore::rank_of(storage, 0, Uint128::from(networth)).unwrap(), rank);
+        }
+
+        // a submission only accesses the nodes along one path of the tree
+        let mut storage = TracingStorage::new(&mut deps.storage);
+        let deps_mut = DepsMut { storage: &mut storage, api: &deps.api, querier: QuerierWrapper::new(&deps.querier) };
+        let msg = ExecuteMsg::SubmitNetWorth { round_id: 0, networth: Uint128::from(25u128), decoys: None, padding: None };
+        execute(deps_mut, mock_env(), mock_info("newcomer", &[]), msg).unwrap();
+        let trace = storage.trace.into_inner();
+        assert!(count_ops(&trace, "read") < 80, "{} reads", count_ops(&trace, "read"));
+        assert!(count_ops(&trace, "write") < 30, "{} writes", count_ops(&trace, "write"));
+    }
+    #[test]
+    fn test_sweep_prize_pool() {
+        let (_, mut deps) = init_helper();
+        let env = mock_env();
+        let mut env_later = mock_env();
+        env_later.block.height += 10;
+        let round_id = create_round_helper(&mut deps, "alice", RoundParams {
+            end_height: Some(env.block.height + 10), entry_fee: Some(coin(100, "uscrt")), ..Default::default()
+        });
+        let submit = ExecuteMsg::SubmitNetWorth { round_id, networth: Uint128::from(1u128), decoys: None, padding: None };
+        execute(deps.as_mut(), env.clone(), mock_info("bob", &coins(100, "uscrt")), submit).unwrap();
+        let withdraw = ExecuteMsg::WithdrawSubmission { round_id, padding: None };
+        execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), withdraw).unwrap();
+
+        // the pool of a round can only be swept once nobody can win it
+        let sweep = ExecuteMsg::SweepPrizePool { round_id, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), sweep.clone());
+        assert_eq!(res.unwrap_err(), ContractError::RoundNotFinalized { round_id });
+        let msg = ExecuteMsg::FinalizeRound { round_id, padding: None };
+        execute(deps.as_mut(), env_later.clone(), mock_info("anyone", &[]), msg).unwrap();
+
+        // a finalized round whose participants all left has no winner to claim its pool
+        let claim = ExecuteMsg::ClaimPrize { round_id, padding: None };
+        let res = execute(deps.as_mut(), env_later.clone(), mock_info("anyone", &[]), claim);
+        assert_eq!(res.unwrap_err(), ContractError::NoPrize { round_id });
+        let res = execute(deps.as_mut(), env_later.clone(), mock_info("bob", &[]), sweep.clone());
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        let res = execute(deps.as_mut(), env_later.clone(), mock_info("alice", &[]), sweep.clone()).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send { to_address: "alice".to_string(), amount: coins(100, "uscrt") })
+        );
+        assert_eq!(PrizePoolStore::load(deps.as_ref().storage, round_id).unwrap(), Uint128::zero());
+        let res = execute(deps.as_mut(), env_later.clone(), mock_info("alice", &[]), sweep);
+        assert_eq!(res.unwrap_err(), ContractError::NoPrize { round_id });
+
+        // a cancelled round only has the fees forfeited before it was cancelled swept,
+        // as its remaining participants can still get theirs back
+        let round_id = create_round_helper(&mut deps, "alice", RoundParams {
+            entry_fee: Some(coin(100, "uscrt")), ..Default::default()
+        });
+        for sender in ["bob", "carol"] {
+            let msg = ExecuteMsg::SubmitNetWorth { round_id, networth: Uint128::from(1u128), decoys: None, padding: None };
+            execute(deps.as_mut(), env.clone(), mock_info(sender, &coins(100, "uscrt")), msg).unwrap();
+        }
+        let withdraw = ExecuteMsg::WithdrawSubmission { round_id, padding: None };
+        execute(deps.as_mut(), env.clone(), mock_info("carol", &[]), withdraw.clone()).unwrap();
+        let msg = ExecuteMsg::CancelRound { round_id, padding: None };
+        execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), msg).unwrap();
+        let sweep = ExecuteMsg::SweepPrizePool { round_id, padding: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), sweep.clone()).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send { to_address: "creator".to_string(), amount: coins(100, "uscrt") })
+        );
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), sweep);
+        assert_eq!(res.unwrap_err(), ContractError::NoPrize { round_id });
+        let res = execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), withdraw).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send { to_address: "bob".to_string(), amount: coins(100, "uscrt") })
+        );
+        assert_eq!(PrizePoolStore::load(deps.as_ref().storage, round_id).unwrap(), Uint128::zero());
     }
 }
\ No newline at end of file
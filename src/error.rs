@@ -1,12 +1,127 @@
-use cosmwasm_std::{StdError, Uint128};
-use thiserror::Error;
+use std::fmt;
 
-#[derive(Error, Debug, PartialEq)]
-pub enum ContractError {
-    #[error("{0}")]
-    // let thiserror implement From<StdError> for you
-    Std(#[from] StdError),
+use cosmwasm_std::{Coin, StdError, Uint128};
+
+use crate::contract::RESPONSE_BLOCK_SIZE;
+use crate::msg::RichieRichPermissions;
 
-    #[error("You have already submitted your networth: {networth:}")]
+/// Every message starts with the error's `code` in brackets, eg: `[unauthorized] ...`, and is padded
+/// with spaces to a multiple of `RESPONSE_BLOCK_SIZE` bytes, like successful responses
+#[derive(Debug, PartialEq)]
+pub enum ContractError {
+    Std(StdError),
+    Unauthorized {},
+    NoPermission {
+        required: Vec<RichieRichPermissions>,
+        granted: Vec<RichieRichPermissions>,
+    },
+    ContractStopped {},
     AlreadySubmittedNetworth { networth: Uint128 },
+    NotSubmitted {},
+    RoundNotFound { round_id: u64 },
+    RoundClosed { round_id: u64 },
+    RoundStillOpen { round_id: u64 },
+    RoundAlreadyFinalized { round_id: u64 },
+    RoundNotFinalized { round_id: u64 },
+    RoundCancelled { round_id: u64 },
+    InvalidEntryFee { expected: Coin },
+    UnexpectedFunds {},
+    NoPrize { round_id: u64 },
+    VerificationRequired { round_id: u64 },
+    NotVerifiedRound { round_id: u64 },
+    EscrowRequired { round_id: u64 },
+    InvalidToken { round_id: u64 },
+    EscrowLocked { round_id: u64 },
+    NothingToWithdraw { round_id: u64 },
+    CommitRequired { round_id: u64 },
+    NotCommitRevealRound { round_id: u64 },
+    AlreadyCommitted {},
+    NotCommitted {},
+    NotRevealPeriod { round_id: u64 },
+    InvalidReveal {},
+}
+
+impl ContractError {
+    /// Stable, machine-readable identifier of the error, which clients can rely on
+    /// instead of the rest of the message
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Std(_) => "std_error",
+            Self::Unauthorized {} => "unauthorized",
+            Self::NoPermission { .. } => "no_permission",
+            Self::ContractStopped {} => "contract_stopped",
+            Self::AlreadySubmittedNetworth { .. } => "already_submitted",
+            Self::NotSubmitted {} => "not_submitted",
+            Self::RoundNotFound { .. } => "round_not_found",
+            Self::RoundClosed { .. } => "round_closed",
+            Self::RoundStillOpen { .. } => "round_still_open",
+            Self::RoundAlreadyFinalized { .. } => "round_already_finalized",
+            Self::RoundNotFinalized { .. } => "round_not_finalized",
+            Self::RoundCancelled { .. } => "round_cancelled",
+            Self::InvalidEntryFee { .. } => "invalid_entry_fee",
+            Self::UnexpectedFunds {} => "unexpected_funds",
+            Self::NoPrize { .. } => "no_prize",
+            Self::VerificationRequired { .. } => "verification_required",
+            Self::NotVerifiedRound { .. } => "not_verified_round",
+            Self::EscrowRequired { .. } => "escrow_required",
+            Self::InvalidToken { .. } => "invalid_token",
+            Self::EscrowLocked { .. } => "escrow_locked",
+            Self::NothingToWithdraw { .. } => "nothing_to_withdraw",
+            Self::CommitRequired { .. } => "commit_required",
+            Self::NotCommitRevealRound { .. } => "not_commit_reveal_round",
+            Self::AlreadyCommitted {} => "already_committed",
+            Self::NotCommitted {} => "not_committed",
+            Self::NotRevealPeriod { .. } => "not_reveal_period",
+            Self::InvalidReveal {} => "invalid_reveal",
+        }
+    }
+
+    /// The error's description, without its code or padding
+    fn message(&self) -> String {
+        match self {
+            Self::Std(err) => err.to_string(),
+            Self::Unauthorized {} => "Unauthorized, or wrong viewing key for this address or viewing key not set".to_string(),
+            Self::NoPermission { required, granted } => format!("No permission to query, requires permissions {required:?}, got permissions {granted:?}"),
+            Self::ContractStopped {} => "This contract is stopped and this action is not allowed".to_string(),
+            Self::AlreadySubmittedNetworth { networth } => format!("You have already submitted your networth: {networth}"),
+            Self::NotSubmitted {} => "You have not submitted your networth".to_string(),
+            Self::RoundNotFound { round_id } => format!("Round {round_id} does not exist"),
+            Self::RoundClosed { round_id } => format!("Round {round_id} is closed for submissions"),
+            Self::RoundStillOpen { round_id } => format!("Round {round_id} cannot be finalized before its deadline"),
+            Self::RoundAlreadyFinalized { round_id } => format!("Round {round_id} has already been finalized"),
+            Self::RoundNotFinalized { round_id } => format!("The winner of round {round_id} is revealed only after the round is finalized"),
+            Self::RoundCancelled { round_id } => format!("Round {round_id} has been cancelled"),
+            Self::InvalidEntryFee { expected } => format!("Exactly {expected} must be sent to enter this round"),
+            Self::UnexpectedFunds {} => "This action does not accept funds".to_string(),
+            Self::NoPrize { round_id } => format!("Round {round_id} has no prize to claim"),
+            Self::VerificationRequired { round_id } => format!("Round {round_id} only accepts networths verified from bank balances"),
+            Self::NotVerifiedRound { round_id } => format!("Round {round_id} does not verify networths"),
+            Self::EscrowRequired { round_id } => format!("Round {round_id} only accepts networths deposited in its escrow token"),
+            Self::InvalidToken { round_id } => format!("Round {round_id} does not accept deposits of this token"),
+            Self::EscrowLocked { round_id } => format!("Deposits of round {round_id} are locked until the round ends"),
+            Self::NothingToWithdraw { round_id } => format!("You have no deposit to withdraw in round {round_id}"),
+            Self::CommitRequired { round_id } => format!("Round {round_id} only accepts networths committed with CommitNetWorth"),
+            Self::NotCommitRevealRound { round_id } => format!("Round {round_id} does not accept commitments"),
+            Self::AlreadyCommitted {} => "You have already committed your networth".to_string(),
+            Self::NotCommitted {} => "You have no commitment to reveal".to_string(),
+            Self::NotRevealPeriod { round_id } => format!("Commitments of round {round_id} can only be revealed between its end and its reveal deadline"),
+            Self::InvalidReveal {} => "The networth and salt do not match your commitment".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ContractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = format!("[{}] {}", self.code(), self.message());
+        let padding = (RESPONSE_BLOCK_SIZE - message.len() % RESPONSE_BLOCK_SIZE) % RESPONSE_BLOCK_SIZE;
+        write!(f, "{message}{}", " ".repeat(padding))
+    }
+}
+
+impl std::error::Error for ContractError {}
+
+impl From<StdError> for ContractError {
+    fn from(err: StdError) -> Self {
+        Self::Std(err)
+    }
 }
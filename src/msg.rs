@@ -1,48 +1,375 @@
-use cosmwasm_std::{Addr, Api, StdResult, Uint128};
+use cosmwasm_std::{Addr, Api, Binary, Coin, StdResult, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use secret_toolkit::permit::Permit;
 
+use crate::state::{ContractStatusLevel, RoundStatus, Snip20Token};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+    /// Defaults to the instantiator
+    pub admin: Option<String>,
+    /// Ascending percentages between 1 and 100 used by the `MyPercentile` queries.
+    /// Defaults to `[10, 25, 50]`
+    pub percentile_buckets: Option<Vec<u8>>,
+    /// Seeds the PRNG used by `CreateViewingKey`
+    pub prng_seed: Binary,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct InstantiateMsg {}
+pub struct MigrateMsg {
+    /// Required when migrating a contract deployed before rounds were introduced,
+    /// as it has no admin. Ignored otherwise
+    pub admin: Option<String>,
+    /// Addresses that submitted to a contract deployed before rounds were introduced.
+    /// Their networths are moved to round 0, along with the richest's. Networths of
+    /// addresses not listed here are lost, as that contract kept no list of participants
+    pub legacy_participants: Option<Vec<String>>,
+    /// Replaces the seed of the PRNG used by `CreateViewingKey`. Existing viewing keys stay valid
+    pub prng_seed: Binary,
+}
 
+/// Every message takes an optional `padding`, which is ignored by the contract. It lets clients
+/// give all their messages the same length, as lengths are visible despite the encryption
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    SubmitNetWorth { networth: Uint128 },
-    SetViewingKey { key: String },
+    CreateRound {
+        end_height: Option<u64>,
+        /// in seconds since epoch
+        end_time: Option<u64>,
+        /// native coin that participants must send along with their submission
+        entry_fee: Option<Coin>,
+        /// When set, networths are the sum of the participants' bank balances in these denoms,
+        /// and can only be submitted with `SubmitVerifiedNetWorth`
+        verified_denoms: Option<Vec<String>>,
+        /// Also counts the participants' delegations in the verified denoms.
+        /// Requires `verified_denoms`, defaults to false
+        include_delegations: Option<bool>,
+        /// When set, networths are the amounts of this token that participants `Send` to the
+        /// contract with a `ReceiveMsg::Deposit`. The deposits are held until the round ends.
+        /// Cannot be combined with `entry_fee` or `verified_denoms`
+        escrow_token: Option<Snip20Token>,
+        /// When set, networths are committed with `CommitNetWorth` while the round is open, and
+        /// revealed with `RevealNetWorth` after it ends, until this block height. Requires
+        /// `end_height`, and cannot be combined with the other submission modes
+        reveal_deadline: Option<u64>,
+        padding: Option<String>,
+    },
+    /// Ends a round created without a deadline, so it can be finalized.
+    /// Can be called by the round's creator or the admin
+    CloseRound {
+        round_id: u64,
+        padding: Option<String>,
+    },
+    /// Cancels a round that has not been finalized. Can be called by the round's creator or the admin
+    CancelRound {
+        round_id: u64,
+        padding: Option<String>,
+    },
+    SubmitNetWorth {
+        round_id: u64,
+        networth: Uint128,
+        /// Addresses whose submissions are read and rewritten unchanged along with yours, so the
+        /// storage access pattern does not reveal who submitted. Decoys that did not submit
+        /// in the round are written as well, and still have no submission afterwards
+        decoys: Option<Vec<Addr>>,
+        padding: Option<String>,
+    },
+    /// Submits your bank balances in the round's verified denoms as your networth,
+    /// as of the current block height. Your delegations in those denoms are added
+    /// if the round includes delegations
+    SubmitVerifiedNetWorth {
+        round_id: u64,
+        /// Like the decoys of `SubmitNetWorth`
+        decoys: Option<Vec<Addr>>,
+        padding: Option<String>,
+    },
+    /// Called by SNIP-20 contracts when tokens are sent to this contract.
+    /// `msg` must be a `ReceiveMsg`
+    Receive {
+        sender: Addr,
+        from: Addr,
+        amount: Uint128,
+        memo: Option<String>,
+        msg: Option<Binary>,
+        padding: Option<String>,
+    },
+    /// Returns your deposit of a round's escrow token once the round has ended
+    Withdraw {
+        round_id: u64,
+        padding: Option<String>,
+    },
+    /// Seals your networth in a round with a reveal deadline, without disclosing it
+    CommitNetWorth {
+        round_id: u64,
+        /// sha256 of your networth as 16 big-endian bytes, followed by the UTF-8 bytes of a salt
+        commitment: Binary,
+        padding: Option<String>,
+    },
+    /// Opens your commitment once the round has ended, which submits the networth.
+    /// Commitments that are not revealed before the deadline do not take part in the round
+    RevealNetWorth {
+        round_id: u64,
+        networth: Uint128,
+        salt: String,
+        padding: Option<String>,
+    },
+    /// Replaces your previously submitted networth while the round is open.
+    /// Not available in rounds that verify, escrow or commit networths
+    UpdateNetWorth {
+        round_id: u64,
+        networth: Uint128,
+        padding: Option<String>,
+    },
+    /// Leaves a round that is still open, or has been cancelled, erasing your submission, your
+    /// winner reveal choice and your viewing key, even if you have no submission left in the round.
+    /// The entry fee is only refunded if the round was cancelled, otherwise it stays in the prize pool.
+    /// An unrevealed commitment takes no part in the round, so it can be withdrawn at any time
+    WithdrawSubmission {
+        round_id: u64,
+        padding: Option<String>,
+    },
+    /// Opt in (or out) of publicly revealing your networth if you win the round
+    SetWinnerReveal {
+        round_id: u64,
+        reveal_networth: bool,
+        padding: Option<String>,
+    },
+    FinalizeRound {
+        round_id: u64,
+        padding: Option<String>,
+    },
+    /// Sends the prize pool of a finalized round to its richest participant. Can be called by anyone
+    ClaimPrize {
+        round_id: u64,
+        padding: Option<String>,
+    },
+    /// Sends the entry fees that nobody can claim or get back to the sender: those forfeited in a
+    /// cancelled round, or the whole prize pool of a finalized round without participants.
+    /// Can be called by the round's creator or the admin
+    SweepPrizePool {
+        round_id: u64,
+        padding: Option<String>,
+    },
+    SetViewingKey {
+        key: String,
+        /// Addresses whose viewing keys are read and rewritten unchanged along with yours.
+        /// Decoys that have no viewing key still have none afterwards
+        decoys: Option<Vec<Addr>>,
+        padding: Option<String>,
+    },
+    /// Generates a viewing key from `entropy` and the contract's PRNG seed, and returns it
+    /// in the response data
+    CreateViewingKey {
+        entropy: String,
+        padding: Option<String>,
+    },
+    /// Permits named `permit_name` that were signed by you are no longer accepted
+    RevokePermit {
+        permit_name: String,
+        padding: Option<String>,
+    },
+    /// Proposes a new admin, who then needs to accept the role with `AcceptAdmin`
+    ChangeAdmin {
+        address: String,
+        padding: Option<String>,
+    },
+    AcceptAdmin {
+        padding: Option<String>,
+    },
+    SetContractStatus {
+        level: ContractStatusLevel,
+        padding: Option<String>,
+    },
+}
+
+/// Messages sent along with SNIP-20 tokens
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    /// Submits the amount sent as the sender's networth in a round that escrows networths
+    Deposit {
+        round_id: u64,
+        /// Like the decoys of `SubmitNetWorth`. Their deposits are rewritten unchanged as well
+        decoys: Option<Vec<Addr>>,
+    },
 }
 
+/// Like `ExecuteMsg`, every query takes an optional `padding` that is ignored
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    AllInfo { 
+    AllInfo {
+        round_id: u64,
         addr: Addr,
         key: String,
+        padding: Option<String>,
     },
     AmIRichest {
+        round_id: u64,
+        addr: Addr,
+        key: String,
+        padding: Option<String>,
+    },
+    MyRank {
+        round_id: u64,
         addr: Addr,
         key: String,
+        padding: Option<String>,
+    },
+    MyPercentile {
+        round_id: u64,
+        addr: Addr,
+        key: String,
+        padding: Option<String>,
+    },
+    /// Runs several queries for the same account, checking the viewing key only once.
+    /// Batches cannot be nested
+    Batch {
+        addr: Addr,
+        key: String,
+        queries: Vec<QueryWithPermit>,
+        padding: Option<String>,
+    },
+    WithPermit {
+        permit: Permit<RichieRichPermissions>,
+        query: QueryWithPermit,
+        padding: Option<String>,
+    },
+    RoundStatus {
+        round_id: u64,
+        padding: Option<String>,
+    },
+    /// Public information about a round, which reveals nothing about individual networths
+    ContestInfo {
+        round_id: u64,
+        padding: Option<String>,
+    },
+    Winner {
+        round_id: u64,
+        padding: Option<String>,
+    },
+    Config {
+        padding: Option<String>,
+    },
+    ContractStatus {
+        padding: Option<String>,
     },
-    //
-    // complete code here
-    // 
 }
 
 impl QueryMsg {
-    pub fn get_validation_params(&self) -> (/* complete code here */) {
-        //
-        // complete code here
-        //
+    pub fn get_validation_params(&self, api: &dyn Api) -> StdResult<(Addr, String)> {
+        match self {
+            Self::AllInfo { addr, key, .. } => {
+                let address = api.addr_validate(addr.as_str())?;
+                Ok((address, key.clone()))
+            }
+            Self::AmIRichest { addr, key, .. } => {
+                let address = api.addr_validate(addr.as_str())?;
+                Ok((address, key.clone()))
+            },
+            Self::MyRank { addr, key, .. } => {
+                let address = api.addr_validate(addr.as_str())?;
+                Ok((address, key.clone()))
+            },
+            Self::MyPercentile { addr, key, .. } => {
+                let address = api.addr_validate(addr.as_str())?;
+                Ok((address, key.clone()))
+            },
+            Self::Batch { addr, key, .. } => {
+                let address = api.addr_validate(addr.as_str())?;
+                Ok((address, key.clone()))
+            },
+            Self::WithPermit { .. } => panic!("This query type does not require authentication"),
+            Self::RoundStatus { .. } => panic!("This query type does not require authentication"),
+            Self::ContestInfo { .. } => panic!("This query type does not require authentication"),
+            Self::Winner { .. } => panic!("This query type does not require authentication"),
+            Self::Config { .. } => panic!("This query type does not require authentication"),
+            Self::ContractStatus { .. } => panic!("This query type does not require authentication"),
+        }
     }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, JsonSchema, Eq, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryWithPermit {
-    //
-    // complete code here
-    //
+    AllInfo { round_id: u64 },
+    AmIRichest { round_id: u64 },
+    MyRank { round_id: u64 },
+    MyPercentile { round_id: u64 },
+    /// Requires the permissions of all the batched queries. Batches cannot be nested
+    Batch { queries: Vec<QueryWithPermit> },
+}
+
+impl QueryWithPermit {
+    /// Permissions that a permit must grant to run this query
+    pub fn required_permissions(&self) -> Vec<RichieRichPermissions> {
+        match self {
+            Self::AllInfo { .. } => vec![RichieRichPermissions::AllInfo],
+            Self::AmIRichest { .. } => vec![RichieRichPermissions::AmIRichest],
+            Self::MyRank { .. } => vec![RichieRichPermissions::MyRank],
+            Self::MyPercentile { .. } => vec![RichieRichPermissions::MyPercentile],
+            Self::Batch { queries } => queries.iter().flat_map(Self::required_permissions).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseStatus {
+    Success,
+    Failure,
+}
+
+/// Returned in the data of execute responses, so callers learn the outcome of their
+/// transaction without a follow-up query
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleAnswer {
+    SubmitNetWorth {
+        status: ResponseStatus,
+        /// whether your networth is now the highest in the round
+        became_richest: bool,
+    },
+    SubmitVerifiedNetWorth {
+        status: ResponseStatus,
+        /// the networth computed from your balances
+        networth: Uint128,
+        became_richest: bool,
+    },
+    UpdateNetWorth {
+        status: ResponseStatus,
+        /// whether you are the richest in the round after the update
+        richest: bool,
+    },
+    WithdrawSubmission {
+        status: ResponseStatus,
+    },
+    Withdraw {
+        status: ResponseStatus,
+        /// amount of the escrow token returned to you
+        amount: Uint128,
+    },
+    CommitNetWorth {
+        status: ResponseStatus,
+    },
+    RevealNetWorth {
+        status: ResponseStatus,
+        became_richest: bool,
+    },
+    SetWinnerReveal {
+        status: ResponseStatus,
+    },
+    SetViewingKey {
+        status: ResponseStatus,
+    },
+    CreateViewingKey {
+        key: String,
+    },
+    RevokePermit {
+        status: ResponseStatus,
+    },
 }
 
 /// We define a custom struct for each query response
@@ -50,17 +377,70 @@ pub enum QueryWithPermit {
 pub enum QueryAnswer {
     AllInfo {
         richest: bool,
-        networth: Uint128
+        networth: Uint128,
+        /// block height of the balances a verified networth was computed from.
+        /// `None` for self-reported networths
+        snapshot_height: Option<u64>,
     },
     AmIRichest {
         richest: bool,
     },
+    MyRank {
+        /// 1 for the richest participant(s)
+        rank: u32,
+        participants: u32,
+    },
+    MyPercentile {
+        /// you are within the top `top_percent`% of participants
+        top_percent: u8,
+    },
+    RoundStatus {
+        status: RoundStatus,
+        end_height: Option<u64>,
+        end_time: Option<u64>,
+    },
+    ContestInfo {
+        /// number of participants with a submission in the round
+        participants: u32,
+        status: RoundStatus,
+        created_height: u64,
+        entry_fee: Option<Coin>,
+        /// entry fees collected so far, in the denom of `entry_fee`
+        prize_pool: Uint128,
+        /// `None` if networths are self-reported
+        verified_denoms: Option<Vec<String>>,
+        /// whether verified networths include delegations
+        include_delegations: bool,
+        /// `None` unless networths are deposits of this token
+        escrow_token: Option<Snip20Token>,
+        /// `None` unless networths are committed, then revealed until this block height
+        reveal_deadline: Option<u64>,
+    },
+    Winner {
+        /// `None` if nobody submitted in the round
+        winner: Option<Addr>,
+        /// only returned if the winner opted in to reveal it
+        networth: Option<Uint128>,
+    },
+    Config {
+        admin: Addr,
+        pending_admin: Option<Addr>,
+        percentile_buckets: Vec<u8>,
+    },
+    ContractStatus {
+        status: ContractStatusLevel,
+    },
+    /// in the same order as the batched queries
+    Batch {
+        answers: Vec<QueryAnswer>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum RichieRichPermissions {
-    //
-    // complete code here
-    //
-}
\ No newline at end of file
+    AllInfo,
+    AmIRichest,
+    MyRank,
+    MyPercentile,
+}
@@ -1,15 +1,34 @@
-use cosmwasm_std::{Addr, Storage, StdResult, Uint128};
-use cosmwasm_storage::{
-    ReadonlySingleton, singleton, Singleton,
-    singleton_read,
-};
-use secret_toolkit::storage::{Item};
+use std::cell::Cell;
+
+use cosmwasm_std::{Addr, Binary, BlockInfo, Coin, Order, Record, Storage, StdError, StdResult, Uint128};
+use cosmwasm_storage::{singleton, Singleton};
+use secret_toolkit::storage::{Item, Keymap};
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 const STATE_KEY: &[u8] = b"state";
+pub const PREFIX_OUTCOMES: &[u8] = b"outcomes";
 pub const PREFIX_BALANCES: &[u8] = b"balances";
+pub const PREFIX_NETWORTHS: &[u8] = b"networths";
+pub const PREFIX_ROUNDS: &[u8] = b"rounds";
+pub const ROUND_COUNT_KEY: &[u8] = b"round_count";
+pub const PREFIX_WINNER_REVEALS: &[u8] = b"winner_reveals";
+pub const PREFIX_RANKING_TREES: &[u8] = b"ranking_trees";
+pub const PREFIX_RANKING_NODES: &[u8] = b"ranking_nodes";
+pub const PREFIX_PARTICIPANT_COUNTS: &[u8] = b"participant_counts";
+pub const PREFIX_CREATION_HEIGHTS: &[u8] = b"creation_heights";
+pub const PREFIX_PRIZE_POOLS: &[u8] = b"prize_pools";
+pub const PREFIX_VERIFIED_DENOMS: &[u8] = b"verified_denoms";
+pub const PREFIX_SNAPSHOT_HEIGHTS: &[u8] = b"snapshot_heights";
+pub const PREFIX_INCLUDE_DELEGATIONS: &[u8] = b"include_delegations";
+pub const PREFIX_ESCROW_TOKENS: &[u8] = b"escrow_tokens";
+pub const PREFIX_DEPOSITS: &[u8] = b"deposits";
+pub const PREFIX_REVEAL_DEADLINES: &[u8] = b"reveal_deadlines";
+pub const PREFIX_COMMITMENTS: &[u8] = b"commitments";
+pub const CONFIG_KEY: &[u8] = b"config";
+pub const CONTRACT_STATUS_KEY: &[u8] = b"contract_status";
+pub const CONTRACT_VERSION_KEY: &[u8] = b"contract_info";
 
 
 #[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
@@ -36,13 +55,27 @@ impl Outcome {
     } 
 }
 
-pub fn state(storage: &mut dyn Storage) -> Singleton<Outcome> {
+/// Each round keeps its own `Outcome`, keyed by round id
+pub static OUTCOMES: Keymap<u64, Outcome> = Keymap::new(PREFIX_OUTCOMES);
+pub struct OutcomeStore {}
+impl OutcomeStore {
+    pub fn load(store: &dyn Storage, round_id: u64) -> StdResult<Outcome> {
+        OUTCOMES
+            .get(store, &round_id)
+            .ok_or_else(|| StdError::not_found(format!("Outcome of round {round_id}")))
+    }
+
+    pub fn save(store: &mut dyn Storage, round_id: u64, outcome: &Outcome) -> StdResult<()> {
+        OUTCOMES.insert(store, &round_id, outcome)
+    }
+}
+
+/// The single `Outcome` of contracts deployed before rounds were introduced.
+/// Only used to migrate such contracts
+pub fn legacy_state(storage: &mut dyn Storage) -> Singleton<Outcome> {
     singleton(storage, STATE_KEY)
 }
 
-pub fn state_read(storage: &dyn Storage) -> ReadonlySingleton<Outcome> {
-    singleton_read(storage, STATE_KEY)
-}   
 
 
 #[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
@@ -52,20 +85,731 @@ pub struct Millionaire {
     pub networth: Uint128,
 }
 
+/// Metadata of an individual competition
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct Round {
+    pub creator: Addr,
+    /// submissions are no longer accepted from this block height onwards
+    pub end_height: Option<u64>,
+    /// submissions are no longer accepted from this time onwards (in seconds since epoch)
+    pub end_time: Option<u64>,
+    pub finalized: bool,
+    /// paid by each participant when submitting, and collected into the round's prize pool
+    pub entry_fee: Option<Coin>,
+    pub cancelled: bool,
+}
+
+impl Round {
+    pub fn new(
+        creator: Addr,
+        end_height: Option<u64>,
+        end_time: Option<u64>,
+        entry_fee: Option<Coin>,
+    ) -> Self {
+        Self {
+            creator,
+            end_height,
+            end_time,
+            finalized: false,
+            entry_fee,
+            cancelled: false,
+        }
+    }
+
+    /// A round closes as soon as either of its deadlines is reached
+    pub fn is_past_deadline(&self, block: &BlockInfo) -> bool {
+        let height_reached = matches!(self.end_height, Some(h) if block.height >= h);
+        let time_reached = matches!(self.end_time, Some(t) if block.time.seconds() >= t);
+        height_reached || time_reached
+    }
+
+    pub fn status(&self, block: &BlockInfo) -> RoundStatus {
+        match (self.cancelled, self.finalized, self.is_past_deadline(block)) {
+            (true, _, _) => RoundStatus::Cancelled,
+            (false, true, _) => RoundStatus::Finalized,
+            (false, false, true) => RoundStatus::Closing,
+            (false, false, false) => RoundStatus::Open,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundStatus {
+    /// accepting submissions
+    Open,
+    /// deadline has passed, so submissions are closed, but the round is not finalized yet
+    Closing,
+    Finalized,
+    /// participants can withdraw their submissions to get their entry fees back
+    Cancelled,
+}
+
+pub static ROUNDS: Item<Round> = Item::new(PREFIX_ROUNDS);
+/// Number of rounds created so far, which is also the id of the next round
+pub static ROUND_COUNT: Item<u64> = Item::new(ROUND_COUNT_KEY);
+/// Block height at which each round was created
+pub static CREATION_HEIGHTS: Item<u64> = Item::new(PREFIX_CREATION_HEIGHTS);
+pub struct RoundStore {}
+impl RoundStore {
+    pub fn may_load(store: &dyn Storage, round_id: u64) -> StdResult<Option<Round>> {
+        ROUNDS.add_suffix(&round_id.to_be_bytes()).may_load(store)
+    }
+
+    /// Saves a new round under the next available id, and returns that id
+    pub fn create(store: &mut dyn Storage, round: &Round, created_height: u64) -> StdResult<u64> {
+        let round_id = ROUND_COUNT.may_load(store)?.unwrap_or_default();
+        ROUNDS.add_suffix(&round_id.to_be_bytes()).save(store, round)?;
+        ROUND_COUNT.save(store, &(round_id + 1))?;
+        CREATION_HEIGHTS.add_suffix(&round_id.to_be_bytes()).save(store, &created_height)?;
+        OutcomeStore::save(store, round_id, &Outcome::init())?;
+
+        Ok(round_id)
+    }
+
+    pub fn load_creation_height(store: &dyn Storage, round_id: u64) -> StdResult<u64> {
+        CREATION_HEIGHTS.add_suffix(&round_id.to_be_bytes()).load(store)
+    }
+
+    pub fn save(store: &mut dyn Storage, round_id: u64, round: &Round) -> StdResult<()> {
+        ROUNDS.add_suffix(&round_id.to_be_bytes()).save(store, round)
+    }
+}
+
+/// Entry fees collected in each round, in the denom of the round's entry fee.
+/// Emptied once the prize is claimed
+pub static PRIZE_POOLS: Item<Uint128> = Item::new(PREFIX_PRIZE_POOLS);
+pub struct PrizePoolStore {}
+impl PrizePoolStore {
+    pub fn load(store: &dyn Storage, round_id: u64) -> StdResult<Uint128> {
+        let pool = PRIZE_POOLS.add_suffix(&round_id.to_be_bytes());
+        Ok(pool.may_load(store)?.unwrap_or_default())
+    }
+
+    pub fn save(store: &mut dyn Storage, round_id: u64, amount: Uint128) -> StdResult<()> {
+        PRIZE_POOLS.add_suffix(&round_id.to_be_bytes()).save(store, &amount)
+    }
+}
+
+/// Denoms whose bank balances make up the networths of a round. Only set for rounds
+/// that verify networths, which then reject self-reported ones
+pub static VERIFIED_DENOMS: Item<Vec<String>> = Item::new(PREFIX_VERIFIED_DENOMS);
+pub struct VerifiedDenomsStore {}
+impl VerifiedDenomsStore {
+    pub fn may_load(store: &dyn Storage, round_id: u64) -> StdResult<Option<Vec<String>>> {
+        VERIFIED_DENOMS.add_suffix(&round_id.to_be_bytes()).may_load(store)
+    }
+
+    pub fn save(store: &mut dyn Storage, round_id: u64, denoms: Vec<String>) -> StdResult<()> {
+        VERIFIED_DENOMS.add_suffix(&round_id.to_be_bytes()).save(store, &denoms)
+    }
+}
+
+/// Whether the verified networths of a round also count the participants' delegations
+pub static INCLUDE_DELEGATIONS: Item<bool> = Item::new(PREFIX_INCLUDE_DELEGATIONS);
+pub struct IncludeDelegationsStore {}
+impl IncludeDelegationsStore {
+    pub fn load(store: &dyn Storage, round_id: u64) -> StdResult<bool> {
+        let include = INCLUDE_DELEGATIONS.add_suffix(&round_id.to_be_bytes());
+        Ok(include.may_load(store)?.unwrap_or_default())
+    }
+
+    pub fn save(store: &mut dyn Storage, round_id: u64, include: bool) -> StdResult<()> {
+        INCLUDE_DELEGATIONS.add_suffix(&round_id.to_be_bytes()).save(store, &include)
+    }
+}
+
+/// A SNIP-20 token contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Snip20Token {
+    pub address: Addr,
+    pub code_hash: String,
+}
+
+/// Token whose deposits make up the networths of a round. Only set for rounds
+/// that escrow networths, which then reject self-reported ones
+pub static ESCROW_TOKENS: Item<Snip20Token> = Item::new(PREFIX_ESCROW_TOKENS);
+pub struct EscrowTokenStore {}
+impl EscrowTokenStore {
+    pub fn may_load(store: &dyn Storage, round_id: u64) -> StdResult<Option<Snip20Token>> {
+        ESCROW_TOKENS.add_suffix(&round_id.to_be_bytes()).may_load(store)
+    }
+
+    pub fn save(store: &mut dyn Storage, round_id: u64, token: &Snip20Token) -> StdResult<()> {
+        ESCROW_TOKENS.add_suffix(&round_id.to_be_bytes()).save(store, token)
+    }
+}
+
+/// Escrow token amounts held by the contract for each participant until they withdraw them, as
+/// 16 big-endian bytes so every entry has the same length. An empty deposit is stored as 0
+pub static DEPOSITS: Item<[u8; 16]> = Item::new(PREFIX_DEPOSITS);
+pub struct DepositStore {}
+impl DepositStore {
+    /// Adds `amount` to the deposit of `account`. Decoys are given an empty deposit, so they are
+    /// read and written like the sender
+    pub fn add(store: &mut dyn Storage, round_id: u64, account: &Addr, amount: Uint128) -> StdResult<()> {
+        let deposits = DEPOSITS
+            .add_suffix(&round_id.to_be_bytes())
+            .add_suffix(account.as_str().as_bytes());
+        let deposit = u128::from_be_bytes(deposits.may_load(store)?.unwrap_or_default());
+        let deposit = Uint128::new(deposit).checked_add(amount)?;
+        deposits.save(store, &deposit.u128().to_be_bytes())
+    }
+
+    /// Removes and returns the deposit of `account`, if it has a non-empty one
+    pub fn take(store: &mut dyn Storage, round_id: u64, account: &Addr) -> StdResult<Option<Uint128>> {
+        let deposits = DEPOSITS
+            .add_suffix(&round_id.to_be_bytes())
+            .add_suffix(account.as_str().as_bytes());
+        let amount = Uint128::new(u128::from_be_bytes(deposits.may_load(store)?.unwrap_or_default()));
+        deposits.remove(store);
+        Ok(Some(amount).filter(|amount| !amount.is_zero()))
+    }
+}
+
+/// Block height until which the commitments of a round can be revealed. Only set for rounds
+/// in which networths are committed while the round is open and revealed after it ends
+pub static REVEAL_DEADLINES: Item<u64> = Item::new(PREFIX_REVEAL_DEADLINES);
+pub struct RevealDeadlineStore {}
+impl RevealDeadlineStore {
+    pub fn may_load(store: &dyn Storage, round_id: u64) -> StdResult<Option<u64>> {
+        REVEAL_DEADLINES.add_suffix(&round_id.to_be_bytes()).may_load(store)
+    }
+
+    pub fn save(store: &mut dyn Storage, round_id: u64, height: u64) -> StdResult<()> {
+        REVEAL_DEADLINES.add_suffix(&round_id.to_be_bytes()).save(store, &height)
+    }
+}
+
+/// Commitments that have not been revealed yet
+pub static COMMITMENTS: Item<Binary> = Item::new(PREFIX_COMMITMENTS);
+pub struct CommitmentStore {}
+impl CommitmentStore {
+    pub fn may_load(store: &dyn Storage, round_id: u64, account: &Addr) -> StdResult<Option<Binary>> {
+        let commitments = COMMITMENTS
+            .add_suffix(&round_id.to_be_bytes())
+            .add_suffix(account.as_str().as_bytes());
+        commitments.may_load(store)
+    }
+
+    pub fn save(store: &mut dyn Storage, round_id: u64, account: &Addr, commitment: &Binary) -> StdResult<()> {
+        let commitments = COMMITMENTS
+            .add_suffix(&round_id.to_be_bytes())
+            .add_suffix(account.as_str().as_bytes());
+        commitments.save(store, commitment)
+    }
+
+    pub fn remove(store: &mut dyn Storage, round_id: u64, account: &Addr) {
+        let commitments = COMMITMENTS
+            .add_suffix(&round_id.to_be_bytes())
+            .add_suffix(account.as_str().as_bytes());
+        commitments.remove(store)
+    }
+}
+
+/// Block height at which a verified networth was computed from the participant's balances, as
+/// 8 big-endian bytes. Blocks start at height 1, so 0 is stored for decoys that have none. Every
+/// entry has the same length, so decoys are written like the sender
+pub static SNAPSHOT_HEIGHTS: Item<[u8; 8]> = Item::new(PREFIX_SNAPSHOT_HEIGHTS);
+pub struct SnapshotHeightStore {}
+impl SnapshotHeightStore {
+    pub fn may_load(store: &dyn Storage, round_id: u64, account: &Addr) -> StdResult<Option<u64>> {
+        let heights = SNAPSHOT_HEIGHTS
+            .add_suffix(&round_id.to_be_bytes())
+            .add_suffix(account.as_str().as_bytes());
+        let height = u64::from_be_bytes(heights.may_load(store)?.unwrap_or_default());
+        Ok(Some(height).filter(|height| *height != 0))
+    }
+
+    pub fn save(store: &mut dyn Storage, round_id: u64, account: &Addr, height: u64) -> StdResult<()> {
+        let heights = SNAPSHOT_HEIGHTS
+            .add_suffix(&round_id.to_be_bytes())
+            .add_suffix(account.as_str().as_bytes());
+        heights.save(store, &height.to_be_bytes())
+    }
+
+    /// Reads the snapshot height of `account` and writes it back unchanged, even if it has none.
+    /// Used for decoys, so their accesses match those of the sender
+    pub fn touch(store: &mut dyn Storage, round_id: u64, account: &Addr) -> StdResult<()> {
+        let heights = SNAPSHOT_HEIGHTS
+            .add_suffix(&round_id.to_be_bytes())
+            .add_suffix(account.as_str().as_bytes());
+        let height = heights.may_load(store)?.unwrap_or_default();
+        heights.save(store, &height)
+    }
+
+    pub fn remove(store: &mut dyn Storage, round_id: u64, account: &Addr) {
+        let heights = SNAPSHOT_HEIGHTS
+            .add_suffix(&round_id.to_be_bytes())
+            .add_suffix(account.as_str().as_bytes());
+        heights.remove(store)
+    }
+}
+
+/// Networths of the original contract, keyed by address only. Only used to migrate them into round 0
 pub static NETWORTHS: Item<Uint128> = Item::new(PREFIX_BALANCES);
+/// Networth submitted by each participant of a round, as a byte that is 1 if there is one,
+/// followed by the networth as 16 big-endian bytes and the id of its ranking node as 8 big-endian
+/// bytes. All zeros are stored for decoys that did not submit. Every entry has the same length
+/// and is the only one keyed by the participant, so decoys are written like the sender
+pub static ROUND_NETWORTHS: Item<[u8; 25]> = Item::new(PREFIX_NETWORTHS);
 pub struct NetWorthStore {}
 impl NetWorthStore {
-    pub fn may_load(store: &dyn Storage, account: &Addr) -> Option<Uint128> {
-        //
-        // complete code here
-        //
-        Some(Uint128::from(0_u128))  // change this line of code
-    }
-
-    pub fn save(store: &mut dyn Storage, account: &Addr, amount: Uint128) -> StdResult<()> {
-        //
-        // complete code here
-        //
-        Ok(()) // change this line of code
+    pub fn may_load(store: &dyn Storage, round_id: u64, account: &Addr) -> StdResult<Option<Uint128>> {
+        let entry = Self::load_entry(store, round_id, account)?;
+        let mut amount = [0u8; 16];
+        amount.copy_from_slice(&entry[1..17]);
+        Ok(Some(Uint128::new(u128::from_be_bytes(amount))).filter(|_| entry[0] == 1))
+    }
+
+    /// Id of the ranking node of the submission of `account`, if it has one
+    pub fn may_load_ranking_id(store: &dyn Storage, round_id: u64, account: &Addr) -> StdResult<Option<u64>> {
+        let entry = Self::load_entry(store, round_id, account)?;
+        let mut id = [0u8; 8];
+        id.copy_from_slice(&entry[17..]);
+        Ok(Some(u64::from_be_bytes(id)).filter(|_| entry[0] == 1))
+    }
+
+    pub fn save(
+        store: &mut dyn Storage,
+        round_id: u64,
+        account: &Addr,
+        amount: Uint128,
+        ranking_id: u64,
+    ) -> StdResult<()> {
+        let balances = ROUND_NETWORTHS
+            .add_suffix(&round_id.to_be_bytes())
+            .add_suffix(account.as_str().as_bytes());
+        let mut entry = [1u8; 25];
+        entry[1..17].copy_from_slice(&amount.u128().to_be_bytes());
+        entry[17..].copy_from_slice(&ranking_id.to_be_bytes());
+        balances.save(store, &entry)
+    }
+
+    /// Reads the networth of `account` and writes it back unchanged, even if it has none.
+    /// Used for decoys, so their accesses match those of the sender
+    pub fn touch(store: &mut dyn Storage, round_id: u64, account: &Addr) -> StdResult<()> {
+        let balances = ROUND_NETWORTHS
+            .add_suffix(&round_id.to_be_bytes())
+            .add_suffix(account.as_str().as_bytes());
+        let entry = balances.may_load(store)?.unwrap_or_default();
+        balances.save(store, &entry)
+    }
+
+    pub fn remove(store: &mut dyn Storage, round_id: u64, account: &Addr) {
+        let balances = ROUND_NETWORTHS
+            .add_suffix(&round_id.to_be_bytes())
+            .add_suffix(account.as_str().as_bytes());
+        balances.remove(store)
+    }
+
+    fn load_entry(store: &dyn Storage, round_id: u64, account: &Addr) -> StdResult<[u8; 25]> {
+        let balances = ROUND_NETWORTHS
+            .add_suffix(&round_id.to_be_bytes())
+            .add_suffix(account.as_str().as_bytes());
+        Ok(balances.may_load(store)?.unwrap_or_default())
+    }
+
+    /// Removes and returns a networth stored by contracts deployed before rounds were introduced,
+    /// which were keyed by address only
+    pub fn take_legacy(store: &mut dyn Storage, account: &Addr) -> StdResult<Option<Uint128>> {
+        let balances = NETWORTHS.add_suffix(account.as_str().as_bytes());
+        let networth = balances.may_load(store)?;
+        balances.remove(store);
+        Ok(networth)
+    }
+}
+
+/// A submission in a round's ranking, which is an AVL tree ordered from richest to poorest.
+/// Among equal networths, earlier submissions (with lower ids) come first, so the head of the
+/// ranking always matches `Outcome.richest`. Each node is stored under its own key and keeps the
+/// size of its subtree, so submissions and ranks only access the nodes along one path
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RankingNode {
+    pub millionaire: Millionaire,
+    left: Option<u64>,
+    right: Option<u64>,
+    height: u32,
+    size: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RankingTree {
+    root: Option<u64>,
+    /// ids are assigned in submission order, which breaks ties between equal networths
+    next_id: u64,
+}
+
+pub static RANKING_TREES: Item<RankingTree> = Item::new(PREFIX_RANKING_TREES);
+pub static RANKING_NODES: Item<RankingNode> = Item::new(PREFIX_RANKING_NODES);
+pub struct RankingStore {}
+impl RankingStore {
+    /// Adds a submission to the ranking, and returns the id of its node, which is needed to remove it.
+    /// The reads and writes are padded to the most that an insertion into a tree of this size can
+    /// make, so their number does not depend on where the submission ranks
+    pub fn insert(store: &mut dyn Storage, round_id: u64, millionaire: Millionaire) -> StdResult<u64> {
+        let mut store = CountingStorage { inner: store, reads: Cell::new(0), writes: 0 };
+        let mut tree = Self::load_tree(&store, round_id)?;
+        let (_, size) = Self::stats(&store, round_id, tree.root)?;
+        let id = tree.next_id;
+        tree.next_id += 1;
+        tree.root = Some(Self::insert_at(&mut store, round_id, tree.root, id, &millionaire)?);
+        Self::trees(round_id).save(&mut store, &tree)?;
+
+        // each level of the path reads a node and the heights of its children before and after
+        // the insertion, and writes the node. At most one level rotates, which reads and writes
+        // the nodes it moves on top of that
+        let levels = Self::max_height(size);
+        while store.reads.get() < 2 + 5 * levels + 10 {
+            Self::load_tree(&store, round_id)?;
+        }
+        while store.writes < 2 + levels + 3 {
+            Self::trees(round_id).save(&mut store, &tree)?;
+        }
+        Ok(id)
+    }
+
+    pub fn remove(store: &mut dyn Storage, round_id: u64, id: u64) -> StdResult<()> {
+        let node = Self::nodes(round_id, id).load(store)?;
+        let mut tree = Self::load_tree(store, round_id)?;
+        tree.root = Self::remove_at(store, round_id, tree.root, id, &node.millionaire)?;
+        Self::nodes(round_id, id).remove(store);
+        Self::trees(round_id).save(store, &tree)
+    }
+
+    /// Number of submissions in the round
+    pub fn len(store: &dyn Storage, round_id: u64) -> StdResult<u32> {
+        let tree = Self::load_tree(store, round_id)?;
+        Ok(Self::stats(store, round_id, tree.root)?.1)
+    }
+
+    /// The head of the ranking, if anybody submitted
+    pub fn first(store: &dyn Storage, round_id: u64) -> StdResult<Option<Millionaire>> {
+        let mut current = Self::load_tree(store, round_id)?.root;
+        let mut first = None;
+        while let Some(id) = current {
+            let node = Self::nodes(round_id, id).load(store)?;
+            current = node.left;
+            first = Some(node.millionaire);
+        }
+        Ok(first)
+    }
+
+    /// Returns the 1-based rank of `networth` in the round. Participants with equal networths
+    /// share the same rank
+    pub fn rank_of(store: &dyn Storage, round_id: u64, networth: Uint128) -> StdResult<u32> {
+        let mut current = Self::load_tree(store, round_id)?.root;
+        let mut rank = 1;
+        while let Some(id) = current {
+            let node = Self::nodes(round_id, id).load(store)?;
+            if node.millionaire.networth > networth {
+                rank += Self::stats(store, round_id, node.left)?.1 + 1;
+                current = node.right;
+            } else {
+                current = node.left;
+            }
+        }
+        Ok(rank)
+    }
+
+    /// The whole ranking, richest first. Reads every node, so it is only used by tests
+    #[cfg(test)]
+    pub fn load(store: &dyn Storage, round_id: u64) -> StdResult<Vec<Millionaire>> {
+        fn collect(store: &dyn Storage, round_id: u64, id: Option<u64>, out: &mut Vec<Millionaire>) -> StdResult<()> {
+            if let Some(id) = id {
+                let node = RankingStore::nodes(round_id, id).load(store)?;
+                collect(store, round_id, node.left, out)?;
+                out.push(node.millionaire);
+                collect(store, round_id, node.right, out)?;
+            }
+            Ok(())
+        }
+        let mut ranking = vec![];
+        collect(store, round_id, Self::load_tree(store, round_id)?.root, &mut ranking)?;
+        Ok(ranking)
+    }
+
+    fn trees(round_id: u64) -> Item<'static, RankingTree> {
+        RANKING_TREES.add_suffix(&round_id.to_be_bytes())
+    }
+
+    fn nodes(round_id: u64, id: u64) -> Item<'static, RankingNode> {
+        RANKING_NODES.add_suffix(&round_id.to_be_bytes()).add_suffix(&id.to_be_bytes())
+    }
+
+    fn load_tree(store: &dyn Storage, round_id: u64) -> StdResult<RankingTree> {
+        Ok(Self::trees(round_id).may_load(store)?.unwrap_or_default())
+    }
+
+    /// Most levels an AVL tree of `size` nodes can have. The sparsest tree of each height has as many
+    /// nodes as the sparsest trees of the two heights below it, plus its root
+    fn max_height(size: u32) -> u32 {
+        let (mut height, mut nodes, mut nodes_below) = (0, 0, 0);
+        while nodes + nodes_below < size {
+            (nodes, nodes_below) = (nodes + nodes_below + 1, nodes);
+            height += 1;
+        }
+        height
+    }
+
+    /// Whether `a` (with node id `a_id`) ranks ahead of `b`
+    fn precedes(a: &Millionaire, a_id: u64, b: &Millionaire, b_id: u64) -> bool {
+        a.networth > b.networth || (a.networth == b.networth && a_id < b_id)
+    }
+
+    /// Height and size of the subtree rooted at `id`
+    fn stats(store: &dyn Storage, round_id: u64, id: Option<u64>) -> StdResult<(u32, u32)> {
+        match id {
+            Some(id) => {
+                let node = Self::nodes(round_id, id).load(store)?;
+                Ok((node.height, node.size))
+            },
+            None => Ok((0, 0)),
+        }
+    }
+
+    /// Inserts a new node in the subtree rooted at `root`, and returns the new root of the subtree
+    fn insert_at(
+        store: &mut dyn Storage,
+        round_id: u64,
+        root: Option<u64>,
+        id: u64,
+        millionaire: &Millionaire,
+    ) -> StdResult<u64> {
+        let root_id = match root {
+            Some(root_id) => root_id,
+            None => {
+                let leaf = RankingNode { millionaire: millionaire.clone(), left: None, right: None, height: 1, size: 1 };
+                Self::nodes(round_id, id).save(store, &leaf)?;
+                return Ok(id);
+            },
+        };
+        let mut node = Self::nodes(round_id, root_id).load(store)?;
+        if Self::precedes(millionaire, id, &node.millionaire, root_id) {
+            node.left = Some(Self::insert_at(store, round_id, node.left, id, millionaire)?);
+        } else {
+            node.right = Some(Self::insert_at(store, round_id, node.right, id, millionaire)?);
+        }
+        Self::balance(store, round_id, root_id, node)
+    }
+
+    /// Unlinks node `id` from the subtree rooted at `root`, and returns the new root of the subtree
+    fn remove_at(
+        store: &mut dyn Storage,
+        round_id: u64,
+        root: Option<u64>,
+        id: u64,
+        millionaire: &Millionaire,
+    ) -> StdResult<Option<u64>> {
+        let root_id = root.ok_or_else(|| StdError::not_found("Ranking node"))?;
+        let mut node = Self::nodes(round_id, root_id).load(store)?;
+        if root_id == id {
+            return match (node.left, node.right) {
+                (left, None) => Ok(left),
+                (None, right) => Ok(right),
+                (Some(left), Some(right)) => {
+                    // the next node in the ranking takes the place of the removed one
+                    let (right, next_id) = Self::remove_first(store, round_id, right)?;
+                    let mut next = Self::nodes(round_id, next_id).load(store)?;
+                    next.left = Some(left);
+                    next.right = right;
+                    Ok(Some(Self::balance(store, round_id, next_id, next)?))
+                },
+            };
+        }
+        if Self::precedes(millionaire, id, &node.millionaire, root_id) {
+            node.left = Self::remove_at(store, round_id, node.left, id, millionaire)?;
+        } else {
+            node.right = Self::remove_at(store, round_id, node.right, id, millionaire)?;
+        }
+        Ok(Some(Self::balance(store, round_id, root_id, node)?))
+    }
+
+    /// Unlinks the first node of the subtree rooted at `root`, and returns the new root of the
+    /// subtree along with the id of the unlinked node
+    fn remove_first(store: &mut dyn Storage, round_id: u64, root: u64) -> StdResult<(Option<u64>, u64)> {
+        let mut node = Self::nodes(round_id, root).load(store)?;
+        match node.left {
+            None => Ok((node.right, root)),
+            Some(left) => {
+                let (left, first) = Self::remove_first(store, round_id, left)?;
+                node.left = left;
+                Ok((Some(Self::balance(store, round_id, root, node)?), first))
+            },
+        }
+    }
+
+    /// Updates the height and size of a node whose children changed, rotates it if its subtrees'
+    /// heights differ by more than one, saves it, and returns the id of the subtree's new root
+    fn balance(store: &mut dyn Storage, round_id: u64, id: u64, mut node: RankingNode) -> StdResult<u64> {
+        let (left_height, _) = Self::stats(store, round_id, node.left)?;
+        let (right_height, _) = Self::stats(store, round_id, node.right)?;
+        if left_height > right_height + 1 {
+            let left_id = node.left.unwrap_or_default();
+            let left = Self::nodes(round_id, left_id).load(store)?;
+            if Self::stats(store, round_id, left.left)?.0 < Self::stats(store, round_id, left.right)?.0 {
+                node.left = Some(Self::rotate_left(store, round_id, left_id, left)?);
+            }
+            return Self::rotate_right(store, round_id, id, node);
+        }
+        if right_height > left_height + 1 {
+            let right_id = node.right.unwrap_or_default();
+            let right = Self::nodes(round_id, right_id).load(store)?;
+            if Self::stats(store, round_id, right.right)?.0 < Self::stats(store, round_id, right.left)?.0 {
+                node.right = Some(Self::rotate_right(store, round_id, right_id, right)?);
+            }
+            return Self::rotate_left(store, round_id, id, node);
+        }
+        Self::save_node(store, round_id, id, node)?;
+        Ok(id)
+    }
+
+    fn rotate_right(store: &mut dyn Storage, round_id: u64, id: u64, mut node: RankingNode) -> StdResult<u64> {
+        let left_id = node.left.ok_or_else(|| StdError::not_found("Ranking node"))?;
+        let mut left = Self::nodes(round_id, left_id).load(store)?;
+        node.left = left.right;
+        Self::save_node(store, round_id, id, node)?;
+        left.right = Some(id);
+        Self::save_node(store, round_id, left_id, left)?;
+        Ok(left_id)
+    }
+
+    fn rotate_left(store: &mut dyn Storage, round_id: u64, id: u64, mut node: RankingNode) -> StdResult<u64> {
+        let right_id = node.right.ok_or_else(|| StdError::not_found("Ranking node"))?;
+        let mut right = Self::nodes(round_id, right_id).load(store)?;
+        node.right = right.left;
+        Self::save_node(store, round_id, id, node)?;
+        right.left = Some(id);
+        Self::save_node(store, round_id, right_id, right)?;
+        Ok(right_id)
+    }
+
+    /// Saves a node after recomputing its height and size from its children
+    fn save_node(store: &mut dyn Storage, round_id: u64, id: u64, mut node: RankingNode) -> StdResult<()> {
+        let (left_height, left_size) = Self::stats(store, round_id, node.left)?;
+        let (right_height, right_size) = Self::stats(store, round_id, node.right)?;
+        node.height = left_height.max(right_height) + 1;
+        node.size = left_size + right_size + 1;
+        Self::nodes(round_id, id).save(store, &node)
+    }
+}
+
+/// Counts the reads and writes made through it, so `RankingStore::insert` can pad them
+struct CountingStorage<'a> {
+    inner: &'a mut dyn Storage,
+    reads: Cell<u32>,
+    writes: u32,
+}
+
+impl Storage for CountingStorage<'_> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.reads.set(self.reads.get() + 1);
+        self.inner.get(key)
+    }
+
+    fn range<'a>(
+        &'a self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Record> + 'a> {
+        self.inner.range(start, end, order)
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.writes += 1;
+        self.inner.set(key, value)
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.writes += 1;
+        self.inner.remove(key)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[cfg_attr(test, derive(Eq, PartialEq))]
+pub struct Config {
+    pub admin: Addr,
+    /// proposed by the current admin, and becomes admin once accepted
+    pub pending_admin: Option<Addr>,
+    /// Ascending percentages (eg: `[10, 25, 50]`) that `MyPercentile` rounds a participant's position up to
+    pub percentile_buckets: Vec<u8>,
+}
+
+pub static CONFIG: Item<Config> = Item::new(CONFIG_KEY);
+pub const DEFAULT_PERCENTILE_BUCKETS: [u8; 3] = [10, 25, 50];
+
+/// Modelled on SNIP-20's contract status, to let the admin freeze the contract during incidents
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractStatusLevel {
+    NormalRun,
+    /// submitting or updating a networth is not allowed
+    StopSubmissions,
+    /// only viewing key and permit management, and changing the status are allowed
+    StopAll,
+}
+
+pub static CONTRACT_STATUS: Item<ContractStatusLevel> = Item::new(CONTRACT_STATUS_KEY);
+
+/// Name and semver of the code that last instantiated or migrated the contract, in the style of cw2
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[cfg_attr(test, derive(Eq, PartialEq))]
+pub struct ContractVersion {
+    pub contract: String,
+    pub version: String,
+}
+
+pub static CONTRACT_VERSION: Item<ContractVersion> = Item::new(CONTRACT_VERSION_KEY);
+
+/// Returns the smallest bucket `b` such that `rank` is within the top `b` percent of
+/// `participants`, or 100 if it does not fit any of the buckets
+pub fn percentile_bucket(buckets: &[u8], rank: u32, participants: u32) -> u8 {
+    buckets
+        .iter()
+        .copied()
+        .find(|b| rank as u64 * 100 <= *b as u64 * participants as u64)
+        .unwrap_or(100)
+}
+
+/// Number of participants who currently have a submission in each round
+pub static PARTICIPANT_COUNTS: Item<u32> = Item::new(PREFIX_PARTICIPANT_COUNTS);
+pub struct ParticipantCountStore {}
+impl ParticipantCountStore {
+    pub fn load(store: &dyn Storage, round_id: u64) -> StdResult<u32> {
+        let count = PARTICIPANT_COUNTS.add_suffix(&round_id.to_be_bytes());
+        Ok(count.may_load(store)?.unwrap_or_default())
+    }
+
+    pub fn save(store: &mut dyn Storage, round_id: u64, participants: u32) -> StdResult<()> {
+        PARTICIPANT_COUNTS.add_suffix(&round_id.to_be_bytes()).save(store, &participants)
+    }
+}
+
+/// Whether a participant agreed to have their networth revealed publicly if they win the round
+pub static WINNER_REVEALS: Item<bool> = Item::new(PREFIX_WINNER_REVEALS);
+pub struct WinnerRevealStore {}
+impl WinnerRevealStore {
+    pub fn load(store: &dyn Storage, round_id: u64, account: &Addr) -> StdResult<bool> {
+        let reveal = WINNER_REVEALS
+            .add_suffix(&round_id.to_be_bytes())
+            .add_suffix(account.as_str().as_bytes());
+        Ok(reveal.may_load(store)?.unwrap_or_default())
+    }
+
+    pub fn save(store: &mut dyn Storage, round_id: u64, account: &Addr, reveal_networth: bool) -> StdResult<()> {
+        let reveal = WINNER_REVEALS
+            .add_suffix(&round_id.to_be_bytes())
+            .add_suffix(account.as_str().as_bytes());
+        reveal.save(store, &reveal_networth)
+    }
+
+    pub fn remove(store: &mut dyn Storage, round_id: u64, account: &Addr) {
+        let reveal = WINNER_REVEALS
+            .add_suffix(&round_id.to_be_bytes())
+            .add_suffix(account.as_str().as_bytes());
+        reveal.remove(store)
     }
 }